@@ -0,0 +1,221 @@
+use std::hint::black_box;
+use std::time::Duration;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::measurement::WallTime;
+use criterion::BatchSize;
+use criterion::BenchmarkGroup;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use indexmap::IndexMap;
+use more_collections::SmallMap;
+use smallvec::SmallVec;
+
+fn configure(group: &mut BenchmarkGroup<'_, WallTime>) {
+    group
+        .sample_size(100)
+        .measurement_time(Duration::from_millis(1000))
+        .warm_up_time(Duration::from_millis(100));
+}
+
+fn data(len: usize) -> Vec<(usize, String)> {
+    (0..len).map(|i| (i, "hello".to_string())).collect()
+}
+
+fn benchmark_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    configure(&mut group);
+
+    // With C = 8: 4 entries stay inline, 8 entries spill on the next insert,
+    // 16 entries are already on the heap.
+    for (name, len) in [("inline", 4), ("at_spill", 8), ("heap", 16)] {
+        let initial_data = data(len);
+        group.bench_function(BenchmarkId::new(name, "SmallMap"), |b| {
+            b.iter_batched_ref(
+                || SmallMap::<_, _, 8>::from_iter(initial_data.clone()),
+                |x| {
+                    x.insert(1000, "new value".to_string());
+                },
+                BatchSize::SmallInput,
+            );
+        });
+        group.bench_function(BenchmarkId::new(name, "IndexMap"), |b| {
+            b.iter_batched_ref(
+                || IndexMap::<_, _>::from_iter(initial_data.clone()),
+                |x| {
+                    x.insert(1000, "new value".to_string());
+                },
+                BatchSize::SmallInput,
+            );
+        });
+    }
+}
+
+fn bench_get<const C: usize>(group: &mut BenchmarkGroup<'_, WallTime>, name: &str, len: usize) {
+    let map = SmallMap::<_, _, C>::from_iter(data(len));
+    let index_map = IndexMap::<_, _>::from_iter(data(len));
+    // The last key is the worst case for a linear scan.
+    let key = len - 1;
+    group.bench_function(BenchmarkId::new(name, "SmallMap"), |b| {
+        b.iter(|| black_box(map.get(black_box(&key))));
+    });
+    group.bench_function(BenchmarkId::new(name, "IndexMap"), |b| {
+        b.iter(|| black_box(index_map.get(black_box(&key))));
+    });
+}
+
+fn benchmark_get(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get");
+    configure(&mut group);
+
+    bench_get::<4>(&mut group, "inline-C:004", 4);
+    bench_get::<64>(&mut group, "inline-C:064", 64);
+    bench_get::<4>(&mut group, "heap-C:004", 64);
+}
+
+fn benchmark_entry_count(c: &mut Criterion) {
+    let mut group = c.benchmark_group("entry_count");
+    configure(&mut group);
+
+    for distinct in [4, 32] {
+        let words = (0..256).map(|i| i % distinct).collect::<Vec<usize>>();
+        let name = format!("distinct:{distinct:0>3}");
+        group.bench_function(BenchmarkId::new(name.clone(), "SmallMap"), |b| {
+            b.iter(|| {
+                let mut map = SmallMap::<usize, usize, 8>::new();
+                for word in &words {
+                    *map.entry(*word).or_insert(0) += 1;
+                }
+                black_box(map)
+            });
+        });
+        group.bench_function(BenchmarkId::new(name, "IndexMap"), |b| {
+            b.iter(|| {
+                let mut map = IndexMap::<usize, usize>::new();
+                for word in &words {
+                    *map.entry(*word).or_insert(0) += 1;
+                }
+                black_box(map)
+            });
+        });
+    }
+}
+
+fn bench_spill<const C: usize>(group: &mut BenchmarkGroup<'_, WallTime>) {
+    let initial_data = data(C);
+    group.bench_function(BenchmarkId::new(format!("C:{C:0>3}"), "SmallMap"), |b| {
+        b.iter_batched_ref(
+            || SmallMap::<_, _, C>::from_iter(initial_data.clone()),
+            |x| {
+                x.insert(C, "spill".to_string());
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    // The migration letting `extend` size the map, which leaves no room for the
+    // new key-value for some `C`.
+    group.bench_function(BenchmarkId::new(format!("C:{C:0>3}"), "extend"), |b| {
+        b.iter_batched(
+            || SmallVec::<[_; C]>::from_iter(initial_data.clone()),
+            |mut sv| {
+                let mut map = IndexMap::new();
+                map.extend(sv.drain(0..sv.len()));
+                map.insert(C, "spill".to_string());
+                map
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    // The migration as done by `SmallMap`, reserving room for the new key-value
+    // up front.
+    group.bench_function(BenchmarkId::new(format!("C:{C:0>3}"), "reserve"), |b| {
+        b.iter_batched(
+            || SmallVec::<[_; C]>::from_iter(initial_data.clone()),
+            |mut sv| {
+                let mut map = IndexMap::with_capacity(sv.len() + 1);
+                map.extend(sv.drain(0..sv.len()));
+                map.insert(C, "spill".to_string());
+                map
+            },
+            BatchSize::SmallInput,
+        );
+    });
+}
+
+fn benchmark_spill(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spill");
+    configure(&mut group);
+
+    bench_spill::<4>(&mut group);
+    // The hash table is exactly full after draining 7 key-values.
+    bench_spill::<7>(&mut group);
+    bench_spill::<16>(&mut group);
+    bench_spill::<64>(&mut group);
+}
+
+fn bench_sequential_insert<const C: usize>(group: &mut BenchmarkGroup<'_, WallTime>) {
+    group.bench_function(BenchmarkId::new(format!("C:{C:0>3}"), "SmallMap"), |b| {
+        b.iter(|| {
+            let mut map = SmallMap::<usize, usize, C>::new();
+            for i in 0..C {
+                map.insert(i, i);
+            }
+            black_box(map)
+        });
+    });
+    group.bench_function(BenchmarkId::new(format!("C:{C:0>3}"), "IndexMap"), |b| {
+        b.iter(|| {
+            let mut map = IndexMap::<usize, usize>::new();
+            for i in 0..C {
+                map.insert(i, i);
+            }
+            black_box(map)
+        });
+    });
+}
+
+fn benchmark_sequential_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sequential_insert");
+    configure(&mut group);
+
+    bench_sequential_insert::<8>(&mut group);
+    bench_sequential_insert::<64>(&mut group);
+}
+
+fn benchmark_collect(c: &mut Criterion) {
+    let mut group = c.benchmark_group("collect");
+    configure(&mut group);
+
+    let input = data(10_000);
+    // A sized iterator is collected straight into an `IndexMap`. `filter` has a
+    // lower size hint of 0, so collecting starts inline and spills once.
+    group.bench_function(BenchmarkId::new("10k-sized", "SmallMap"), |b| {
+        b.iter(|| black_box(SmallMap::<_, _, 8>::from_iter(input.iter().cloned())));
+    });
+    group.bench_function(BenchmarkId::new("10k-unsized", "SmallMap"), |b| {
+        b.iter(|| {
+            black_box(SmallMap::<_, _, 8>::from_iter(
+                input.iter().filter(|_| true).cloned(),
+            ))
+        });
+    });
+    group.bench_function(BenchmarkId::new("10k-unsized", "IndexMap"), |b| {
+        b.iter(|| {
+            black_box(IndexMap::<_, _>::from_iter(
+                input.iter().filter(|_| true).cloned(),
+            ))
+        });
+    });
+}
+
+criterion_group!(
+    benches,
+    benchmark_insert,
+    benchmark_get,
+    benchmark_entry_count,
+    benchmark_spill,
+    benchmark_collect,
+    benchmark_sequential_insert
+);
+criterion_main!(benches);
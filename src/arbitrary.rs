@@ -0,0 +1,79 @@
+//! [`Arbitrary`] implementations for the collections of this crate.
+//!
+//! Collections are generated as a sequence of insertions, so duplicates are
+//! removed and the data moves to the heap once it exceeds the inline capacity,
+//! exactly like it would for user code.
+
+use core::hash::BuildHasher;
+use core::hash::Hash;
+
+use arbitrary::Arbitrary;
+use arbitrary::Result;
+use arbitrary::Unstructured;
+
+use crate::SmallMap;
+#[cfg(feature = "smallset")]
+use crate::SmallSet;
+
+impl<'a, K, V, const C: usize, S> Arbitrary<'a> for SmallMap<K, V, C, S>
+where
+    K: Arbitrary<'a> + Hash + Eq,
+    V: Arbitrary<'a>,
+    S: BuildHasher + Default,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        u.arbitrary_iter()?.collect()
+    }
+
+    fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
+        u.arbitrary_take_rest_iter()?.collect()
+    }
+}
+
+#[cfg(feature = "smallset")]
+impl<'a, T, const C: usize, S> Arbitrary<'a> for SmallSet<T, C, S>
+where
+    T: Arbitrary<'a> + Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        u.arbitrary_iter()?.collect()
+    }
+
+    fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
+        u.arbitrary_take_rest_iter()?.collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn arbitrary_generates_valid_inline_and_heap_collections() {
+        // Each insertion reads a "continue" flag, a key and a value.
+        let bytes = (0..=255u8)
+            .flat_map(|i| [1, i % 16, i])
+            .collect::<Vec<u8>>();
+        let mut seen_inline = false;
+        let mut seen_heap = false;
+        for len in 0..64 {
+            let mut u = Unstructured::new(&bytes[..len * 3]);
+            let map = SmallMap::<u8, u8, 4>::arbitrary(&mut u).unwrap();
+            let keys = map.keys().collect::<HashSet<_>>();
+            assert_eq!(map.len(), keys.len());
+            assert_eq!(map.len() <= 4, map.is_inline());
+            seen_inline |= map.is_inline();
+            seen_heap |= !map.is_inline();
+
+            let u = Unstructured::new(&bytes[..len * 3]);
+            let set = SmallSet::<u8, 4>::arbitrary_take_rest(u).unwrap();
+            assert_eq!(set.len(), set.iter().collect::<HashSet<_>>().len());
+            assert_eq!(set.len() <= 4, set.is_inline());
+        }
+        assert!(seen_inline);
+        assert!(seen_heap);
+    }
+}
@@ -0,0 +1,131 @@
+//! [`BorshSerialize`] and [`BorshDeserialize`] implementations for the
+//! collections of this crate.
+//!
+//! Collections are serialized as a `u32` length followed by their key-values
+//! (or values) in insertion order. Deserialization inserts them in that order,
+//! moving the data to the heap once it exceeds the inline capacity.
+
+use core::hash::BuildHasher;
+use core::hash::Hash;
+
+use borsh::io;
+use borsh::BorshDeserialize;
+use borsh::BorshSerialize;
+
+use crate::SmallMap;
+#[cfg(feature = "smallset")]
+use crate::SmallSet;
+
+fn serialize_len<W: io::Write>(len: usize, writer: &mut W) -> io::Result<()> {
+    u32::try_from(len)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "collection is too long"))?
+        .serialize(writer)
+}
+
+impl<K, V, const C: usize, S> BorshSerialize for SmallMap<K, V, C, S>
+where
+    K: BorshSerialize,
+    V: BorshSerialize,
+{
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        serialize_len(self.len(), writer)?;
+        for (key, value) in self.iter() {
+            key.serialize(writer)?;
+            value.serialize(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<K, V, const C: usize, S> BorshDeserialize for SmallMap<K, V, C, S>
+where
+    K: BorshDeserialize + Hash + Eq,
+    V: BorshDeserialize,
+    S: BuildHasher + Default,
+{
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let len = u32::deserialize_reader(reader)?;
+        let mut map = Self::default();
+        for _ in 0..len {
+            let key = K::deserialize_reader(reader)?;
+            let value = V::deserialize_reader(reader)?;
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(feature = "smallset")]
+impl<T, const C: usize, S> BorshSerialize for SmallSet<T, C, S>
+where
+    T: BorshSerialize,
+{
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        serialize_len(self.len(), writer)?;
+        for value in self.iter() {
+            value.serialize(writer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "smallset")]
+impl<T, const C: usize, S> BorshDeserialize for SmallSet<T, C, S>
+where
+    T: BorshDeserialize + Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let len = u32::deserialize_reader(reader)?;
+        let mut set = Self::from_keys(SmallMap::default());
+        for _ in 0..len {
+            set.insert(T::deserialize_reader(reader)?);
+        }
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn small_map_round_trip() {
+        fn test<const C: usize>(len: u32, inline: bool) {
+            let map: SmallMap<_, _, C> =
+                SmallMap::from_iter((0..len).map(|i| (i, format!("value {i}"))));
+            assert_eq!(inline, map.is_inline());
+
+            let bytes = borsh::to_vec(&map).unwrap();
+            let actual: SmallMap<u32, String, C> = borsh::from_slice(&bytes).unwrap();
+            assert_eq!(inline, actual.is_inline());
+            assert_eq!(map, actual);
+        }
+        test::<4>(0, true);
+        test::<4>(3, true);
+        test::<4>(10, false);
+    }
+
+    #[test]
+    fn small_set_round_trip() {
+        fn test<const C: usize>(len: u64, inline: bool) {
+            let set: SmallSet<_, C> = SmallSet::from_iter(0..len);
+            assert_eq!(inline, set.is_inline());
+
+            let bytes = borsh::to_vec(&set).unwrap();
+            let actual: SmallSet<u64, C> = borsh::from_slice(&bytes).unwrap();
+            assert_eq!(inline, actual.is_inline());
+            assert_eq!(set, actual);
+        }
+        test::<4>(0, true);
+        test::<4>(3, true);
+        test::<4>(10, false);
+    }
+
+    #[test]
+    fn small_map_uses_the_same_encoding_as_a_vec_of_pairs() {
+        let map: SmallMap<u8, u16, 2> = SmallMap::from_iter([(3, 30), (1, 10), (2, 20)]);
+        let vec: Vec<(u8, u16)> = vec![(3, 30), (1, 10), (2, 20)];
+        assert_eq!(borsh::to_vec(&vec).unwrap(), borsh::to_vec(&map).unwrap());
+    }
+}
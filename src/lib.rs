@@ -39,11 +39,33 @@
 //! is called. See more in the [IndexMap](https://docs.rs/indexmap/1.7.0/indexmap/map/struct.IndexMap.html#order) documentation.
 //!
 //! # Crate features
-//! All features are _disabled_ by default. The options are:
+//! All collection features are _disabled_ by default. The options are:
 //! - `hashsetmultimap`
 //! - `hashvecmultimap`
 //! - `indexsetmultimap`
 //! - `indexvecmultimap`
+//! - `smallmap`
+//! - `smallset`
+//! - `vecmap`
+//!
+//! The `std` feature is _enabled_ by default. Disable it and enable `alloc`
+//! instead to use the crate in a `no_std` environment with a global
+//! allocator, for example with `--no-default-features --features
+//! alloc,smallmap`.
+//!
+//! Optional integrations with other crates:
+//! - `rayon`: parallel iterators for `SmallMap`, and parallel collection into
+//!   `SmallMap` and `SmallSet`.
+//! - `arbitrary`: [`arbitrary::Arbitrary`](https://docs.rs/arbitrary)
+//!   implementations for `SmallMap` and `SmallSet`.
+//! - `proptest`: [proptest](https://docs.rs/proptest) strategies for
+//!   `SmallMap` and `SmallSet`, see the `strategy` module.
+//! - `borsh`: [borsh](https://docs.rs/borsh) serialization for `SmallMap` and
+//!   `SmallSet`.
+//!
+//! The `stats` feature makes every `SmallMap` count how often it moved to the
+//! heap and how many linear scans it did inline, to help choose its inline
+//! capacity. Without the feature this instrumentation compiles to nothing.
 //!
 //! [`HashMap`]: std::collections::HashMap
 //! [`HashSet`]: std::collections::HashSet
@@ -72,6 +94,10 @@ pub mod collections {
     pub use std::collections::{hash_map, hash_set, HashMap, HashSet};
 }
 
+#[cfg(all(feature = "arbitrary", feature = "smallmap"))]
+mod arbitrary;
+#[cfg(all(feature = "borsh", feature = "smallmap"))]
+mod borsh;
 mod multimap;
 #[cfg(all(feature = "indexmap", feature = "smallvec", feature = "smallmap"))]
 pub mod small_map;
@@ -83,6 +109,8 @@ pub mod small_map;
 ))]
 pub mod small_set;
 
+#[cfg(all(feature = "proptest", feature = "smallmap"))]
+pub mod strategy;
 #[cfg(feature = "vecmap")]
 pub mod vec_map;
 
@@ -1,19 +1,43 @@
 use crate::collections::hash_map::RandomState;
+use crate::collections::HashMap;
+#[cfg(not(feature = "std"))]
+use alloc::borrow::ToOwned;
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
 use core::cmp::Ordering;
 use core::fmt;
 use core::fmt::Debug;
 use core::fmt::Formatter;
 use core::hash::BuildHasher;
 use core::iter::FusedIterator;
+use core::iter::Rev;
 use core::mem;
 use core::ops::Index;
 use core::ops::IndexMut;
+use core::ops::RangeBounds;
 
 use ::core::hash::Hash;
+use indexmap::map::MutableKeys;
 use indexmap::Equivalent;
 use indexmap::IndexMap;
+use indexmap::TryReserveError;
 use smallvec::SmallVec;
 
+mod builder;
+mod frozen;
+#[cfg(feature = "rayon")]
+pub mod rayon;
+#[cfg(feature = "stats")]
+mod stats;
+
+pub use builder::SmallMapBuilder;
+pub use frozen::FrozenSmallMap;
+#[cfg(feature = "stats")]
+pub use stats::SmallMapStats;
+
 /// A map-like container that can store a specified number of elements inline.
 ///
 /// `SmallMap` shares most of its API with, and behaves like
@@ -27,6 +51,25 @@ use smallvec::SmallVec;
 /// exceed `C` _most of the time_ but it still needs to support cases where the
 /// data _does_ exceed `C`.
 ///
+/// Inline lookups are linear scans over the stored keys, so filling a map
+/// inline up to `C` costs O(C²) key comparisons. This is faster than hashing
+/// for small `C`, but for large `C` (say 64) with expensive key comparisons a
+/// smaller `C` or an `IndexMap` may perform better, see the `small_map`
+/// benchmarks.
+///
+/// Once on the heap, data only moves back inline after the length has dropped
+/// to the downgrade threshold, half the inline capacity (`C / 2`) by default.
+/// This gap avoids repeated transitions for a map whose length hovers around
+/// `C`. Use [`SmallMapBuilder`] to configure a different threshold.
+///
+/// The hasher `S` is only used once the data has moved to the heap, inline
+/// storage ignores it. When the map moves to the heap, the `IndexMap` is
+/// created with `S::default()`.
+///
+/// Moving to the heap allocates the `IndexMap` once, sized for the inline
+/// key-values and the key-value being inserted, and moves them over in a single
+/// pass. The inline buffer itself never allocates.
+///
 /// # Example
 ///
 /// ```
@@ -48,37 +91,100 @@ use smallvec::SmallVec;
 #[derive(Clone)]
 pub struct SmallMap<K, V, const C: usize, S = RandomState> {
     data: MapData<K, V, C, S>,
+    // Heap data is moved inline once its length drops below this, see
+    // `downgrade_if_small`.
+    downgrade_below: usize,
+    #[cfg(feature = "stats")]
+    stats: stats::Counters,
 }
 
+// The heap variant is boxed so that the size of `SmallMap` is dominated by the
+// inline buffer instead of the `IndexMap`.
 #[derive(Debug, Clone)]
 enum MapData<K, V, const C: usize, S = RandomState> {
     Inline(SmallVec<[(K, V); C]>),
-    Heap(IndexMap<K, V, S>),
+    Heap(Box<IndexMap<K, V, S>>),
 }
 
 impl<K, V, const C: usize> SmallMap<K, V, C> {
     /// Create a new map.
+    ///
+    /// With an inline capacity of `0` the map is always stored on the heap.
     pub fn new() -> Self {
-        debug_assert!(
-                C > 0,
-                "Cannot instantiate SmallMap with no inline capacity, use positive capacity or use IndexMap instead",
-            );
-        SmallMap {
-            data: MapData::Inline(SmallVec::new()),
-        }
+        Self::from_data(MapData::default())
     }
 
     // Helper method for macro, don't use directly.
+    //
+    // The caller must guarantee that `inline` contains no duplicate keys, this
+    // is not checked. Use `SmallMap::try_from()` or `SmallMap::from_vec_dedup()`
+    // for unchecked input.
     #[doc(hidden)]
     pub const fn from_const_unchecked(inline: SmallVec<[(K, V); C]>) -> Self {
-        Self {
-            data: MapData::Inline(inline),
-        }
+        Self::from_data(MapData::Inline(inline))
     }
 }
 
 impl<K, V, const C: usize, S> SmallMap<K, V, C, S> {
+    /// The number of key-values that can be stored inline, `C`.
+    pub const INLINE_CAPACITY: usize = C;
+
+    /// Returns `true` if a map with `len` key-values is created inline, for
+    /// example when collecting from an iterator. Without inline capacity the
+    /// data is always on the heap, even when empty.
+    ///
+    /// ```
+    /// use more_collections::SmallMap;
+    ///
+    /// const FITS: bool = SmallMap::<u8, u8, 4>::fits_inline(4);
+    /// assert!(FITS);
+    /// assert!(!SmallMap::<u8, u8, 4>::fits_inline(5));
+    /// ```
+    pub const fn fits_inline(len: usize) -> bool {
+        C > 0 && len <= C
+    }
+
+    /// By default, heap maps are only moved inline once their length drops to
+    /// this threshold, which is strictly below `C`. The gap prevents a map that
+    /// hovers around `C` entries from moving between heap and inline storage
+    /// on every insert and remove.
+    const INLINE_THRESHOLD: usize = C / 2;
+
+    const fn from_data(data: MapData<K, V, C, S>) -> Self {
+        Self {
+            data,
+            downgrade_below: if C > 0 { Self::INLINE_THRESHOLD + 1 } else { 0 },
+            #[cfg(feature = "stats")]
+            stats: stats::Counters::new(),
+        }
+    }
+
+    /// Returns the counters collected over the lifetime of this map, see
+    /// [`SmallMapStats`].
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> SmallMapStats {
+        self.stats.snapshot()
+    }
+
+    // Counts a move to the heap when the `stats` feature is enabled.
+    #[inline(always)]
+    fn record_spill(&self) {
+        #[cfg(feature = "stats")]
+        self.stats.record_spill();
+    }
+
+    // Counts a linear scan for a key when the data is inline and the `stats`
+    // feature is enabled.
+    #[inline(always)]
+    fn record_inline_scan(&self) {
+        #[cfg(feature = "stats")]
+        if self.is_inline() {
+            self.stats.record_inline_scan();
+        }
+    }
+
     /// The number of key-values stored in the map.
+    #[inline]
     pub fn len(&self) -> usize {
         match &self.data {
             MapData::Inline(sv) => sv.len(),
@@ -87,23 +193,141 @@ impl<K, V, const C: usize, S> SmallMap<K, V, C, S> {
     }
 
     /// Returns `true` if the map is empty.
+    #[inline]
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
     /// The memory capacity that will be allocated inline. If the nubmer of
     /// values exceeds the inline capacity, the map will move to the heap.
+    #[inline]
     pub fn inline_capacity(&self) -> usize {
         C
     }
 
+    /// The number of key-values the map can hold without reallocating. This is
+    /// the inline capacity if the data is stored inline.
+    pub fn capacity(&self) -> usize {
+        match &self.data {
+            MapData::Inline(_) => C,
+            MapData::Heap(map) => map.capacity(),
+        }
+    }
+
     /// Is the data contained by this map stored inline (`true`) or on the heap
     /// (`false`).
+    #[inline]
     pub fn is_inline(&self) -> bool {
         matches!(self.data, MapData::Inline(_))
     }
 
+    /// Removes all key-values from the map.
+    ///
+    /// The data is moved inline, so a map that was on the heap releases its
+    /// heap allocation. A map without inline capacity, or one built with a
+    /// downgrade ratio of `0.0`, stays on the heap and keeps its capacity.
+    pub fn clear(&mut self) {
+        match &mut self.data {
+            // Without inline capacity this is always `0`.
+            MapData::Heap(map) if self.downgrade_below == 0 => map.clear(),
+            _ => self.data = MapData::Inline(SmallVec::new()),
+        }
+    }
+
+    /// Keeps the first `len` key-values and drops the rest. Does nothing if
+    /// `len` is greater than or equal to the length of the map.
+    ///
+    /// If the remaining data fits the downgrade threshold, it is moved inline.
+    pub fn truncate(&mut self, len: usize) {
+        match &mut self.data {
+            MapData::Inline(vec) => vec.truncate(len),
+            MapData::Heap(map) => {
+                map.truncate(len);
+                self.downgrade_if_small();
+            }
+        }
+    }
+
+    /// Returns the key-values as a slice in insertion order if the data is
+    /// stored inline, or `None` if it is stored on the heap.
+    pub fn as_slice(&self) -> Option<&[(K, V)]> {
+        match &self.data {
+            MapData::Inline(vec) => Some(vec.as_slice()),
+            MapData::Heap(_) => None,
+        }
+    }
+
+    /// Returns the underlying `IndexMap` if the data is stored on the heap, or
+    /// `None` if it is stored inline.
+    pub fn as_index_map(&self) -> Option<&IndexMap<K, V, S>> {
+        match &self.data {
+            MapData::Inline(_) => None,
+            MapData::Heap(map) => Some(map),
+        }
+    }
+
+    /// Returns the underlying `IndexMap` mutably if the data is stored on the
+    /// heap, or `None` if it is stored inline.
+    ///
+    /// Removing key-values through the `IndexMap` does not move the data
+    /// inline, use [`SmallMap::shrink_to_fit`] afterwards if needed.
+    pub fn as_index_map_mut(&mut self) -> Option<&mut IndexMap<K, V, S>> {
+        match &mut self.data {
+            MapData::Inline(_) => None,
+            MapData::Heap(map) => Some(map),
+        }
+    }
+
+    /// Returns the key-values in `range` as a slice in insertion order if the
+    /// data is stored inline, or `None` if it is stored on the heap or `range`
+    /// is out of bounds.
+    pub fn get_range<R: RangeBounds<usize>>(&self, range: R) -> Option<&[(K, V)]> {
+        match &self.data {
+            MapData::Inline(vec) => {
+                vec.get((range.start_bound().cloned(), range.end_bound().cloned()))
+            }
+            MapData::Heap(_) => None,
+        }
+    }
+
+    /// Returns the first key-value in insertion order, or `None` if the map is
+    /// empty.
+    pub fn first(&self) -> Option<(&K, &V)> {
+        match &self.data {
+            MapData::Inline(vec) => vec.first().map(|(k, v)| (k, v)),
+            MapData::Heap(map) => map.first(),
+        }
+    }
+
+    /// Returns the first key-value in insertion order with a mutable reference
+    /// to the value, or `None` if the map is empty.
+    pub fn first_mut(&mut self) -> Option<(&K, &mut V)> {
+        match &mut self.data {
+            MapData::Inline(vec) => vec.first_mut().map(|(k, v)| (&*k, v)),
+            MapData::Heap(map) => map.first_mut(),
+        }
+    }
+
+    /// Returns the last key-value in insertion order, or `None` if the map is
+    /// empty.
+    pub fn last(&self) -> Option<(&K, &V)> {
+        match &self.data {
+            MapData::Inline(vec) => vec.last().map(|(k, v)| (k, v)),
+            MapData::Heap(map) => map.last(),
+        }
+    }
+
+    /// Returns the last key-value in insertion order with a mutable reference
+    /// to the value, or `None` if the map is empty.
+    pub fn last_mut(&mut self) -> Option<(&K, &mut V)> {
+        match &mut self.data {
+            MapData::Inline(vec) => vec.last_mut().map(|(k, v)| (&*k, v)),
+            MapData::Heap(map) => map.last_mut(),
+        }
+    }
+
     /// Returns an iterator over the key-values in insertion order.
+    #[inline]
     pub fn iter(&'_ self) -> Iter<'_, K, V> {
         match &self.data {
             MapData::Inline(vec) => Iter::Inline(vec.iter()),
@@ -126,12 +350,272 @@ impl<K, V, const C: usize, S> SmallMap<K, V, C, S> {
         }
     }
 
+    /// Returns an iterator over the values in insertion order.
+    pub fn values(&self) -> Values<'_, K, V> {
+        match &self.data {
+            MapData::Inline(vec) => Values::Inline(vec.iter()),
+            MapData::Heap(map) => Values::Heap(map.values()),
+        }
+    }
+
+    /// Returns an iterator over mutable references to the values in insertion
+    /// order.
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        match &mut self.data {
+            MapData::Inline(vec) => ValuesMut::Inline(vec.iter_mut()),
+            MapData::Heap(map) => ValuesMut::Heap(map.values_mut()),
+        }
+    }
+
+    /// Calls `f` with every key and a mutable reference to its value, in
+    /// insertion order.
+    pub fn apply<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V),
+    {
+        self.iter_mut().for_each(|(k, v)| f(k, v));
+    }
+
+    /// Returns the key of the first value in insertion order for which `pred`
+    /// returns `true`, or `None` if there is no such value.
+    ///
+    /// Computational complexity: O(n)
+    pub fn find_key<P>(&self, mut pred: P) -> Option<&K>
+    where
+        P: FnMut(&V) -> bool,
+    {
+        self.iter().find(|(_k, v)| pred(v)).map(|(k, _v)| k)
+    }
+
+    /// Returns `true` if the map contains a value equal to `value`.
+    ///
+    /// Computational complexity: O(n)
+    pub fn contains_value(&self, value: &V) -> bool
+    where
+        V: PartialEq,
+    {
+        self.values().any(|v| v == value)
+    }
+
+    /// Returns an iterator over the key-values in reverse insertion order.
+    pub fn rev_iter(&self) -> Rev<Iter<'_, K, V>> {
+        self.iter().rev()
+    }
+
+    /// Returns an iterator over the keys in reverse insertion order.
+    pub fn keys_rev(&self) -> Rev<Keys<'_, K, V>> {
+        self.keys().rev()
+    }
+
+    /// Returns an iterator over the values in reverse insertion order.
+    pub fn values_rev(&self) -> Rev<Values<'_, K, V>> {
+        self.values().rev()
+    }
+
+    /// Removes the key-values in the positional `range` and yields them, in
+    /// order. The order of the remaining key-values is preserved.
+    ///
+    /// The range is removed even if the iterator is not consumed. The data
+    /// stays on the heap, use [`SmallMap::compact`] afterwards to move it
+    /// inline.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end, or if the end
+    /// is greater than the length of the map.
+    ///
+    /// Computational complexity: O(n)
+    pub fn drain_range<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, K, V, C> {
+        match &mut self.data {
+            MapData::Inline(vec) => Drain::Inline(vec.drain(range)),
+            MapData::Heap(map) => Drain::Heap(map.drain(range)),
+        }
+    }
+
+    /// Returns an iterator that removes and yields the key-values for which
+    /// `pred` returns `true`, in order.
+    ///
+    /// The order of the remaining key-values is preserved. If the iterator is
+    /// dropped before it is exhausted, the key-values that were not yet visited
+    /// are kept. Once the iterator is dropped, the data is moved inline if it
+    /// fits the downgrade threshold.
+    ///
+    /// Computational complexity: O(n) per removed key-value
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, C, S, F>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        ExtractIf {
+            map: self,
+            index: 0,
+            pred,
+        }
+    }
+
+    /// Moves the key-value at index `from` to index `to`, shifting the
+    /// key-values in between.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` or `to` are out of bounds.
+    ///
+    /// Computational complexity: O(n)
+    pub fn move_index(&mut self, from: usize, to: usize) {
+        match &mut self.data {
+            MapData::Inline(vec) if from < to => vec[from..=to].rotate_left(1),
+            MapData::Inline(vec) => vec[to..=from].rotate_right(1),
+            MapData::Heap(map) => map.move_index(from, to),
+        }
+    }
+
+    /// Swaps the positions of the key-values at index `a` and `b`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` or `b` are out of bounds.
+    ///
+    /// Computational complexity: O(1)
+    pub fn swap_indices(&mut self, a: usize, b: usize) {
+        match &mut self.data {
+            MapData::Inline(vec) => vec.swap(a, b),
+            MapData::Heap(map) => map.swap_indices(a, b),
+        }
+    }
+
+    /// Rotates the key-values in place such that the key-value at index `mid`
+    /// becomes the first one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid > len`.
+    ///
+    /// Computational complexity: O(n)
+    pub fn rotate_left(&mut self, mid: usize) {
+        assert!(mid <= self.len(), "index out of bounds");
+        match &mut self.data {
+            MapData::Inline(vec) => vec.rotate_left(mid),
+            MapData::Heap(map) => {
+                // Rotate by reversing both parts and then the whole map, with
+                // swaps that update the hash table in place.
+                let len = map.len();
+                Self::reverse_heap(map, 0, mid);
+                Self::reverse_heap(map, mid, len);
+                Self::reverse_heap(map, 0, len);
+            }
+        }
+    }
+
+    /// Rotates the key-values in place such that the last `k` key-values
+    /// become the first ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k > len`.
+    ///
+    /// Computational complexity: O(n)
+    pub fn rotate_right(&mut self, k: usize) {
+        assert!(k <= self.len(), "index out of bounds");
+        self.rotate_left(self.len() - k);
+    }
+
+    // Reverses the order of the key-values in `start..end` of a heap map.
+    fn reverse_heap(map: &mut IndexMap<K, V, S>, start: usize, end: usize) {
+        let (mut low, mut high) = (start, end);
+        while low + 1 < high {
+            high -= 1;
+            map.swap_indices(low, high);
+            low += 1;
+        }
+    }
+
+    /// Returns `true` if the key-values of this map are equal to the key-value
+    /// pairs produced by `other`, in the same order.
+    ///
+    /// This compares without allocating, which makes it convenient for
+    /// assertions against a literal list of pairs.
+    pub fn iter_eq<I>(&self, other: I) -> bool
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: PartialEq,
+        V: PartialEq,
+    {
+        let mut other = other.into_iter();
+        self.iter()
+            .all(|(k, v)| matches!(other.next(), Some((ok, ov)) if *k == ok && *v == ov))
+            && other.next().is_none()
+    }
+
+    // Moves the data inline if it is on the heap and its length is below
+    // `downgrade_below`, which is at most `C + 1`.
+    pub(crate) fn downgrade_if_small(&mut self) {
+        if let MapData::Heap(map) = &mut self.data {
+            if map.len() < self.downgrade_below {
+                self.data = MapData::Inline(map.drain(..).collect());
+            }
+        }
+    }
+
+    // Removes and returns the first key-value at or after `*index` for which
+    // `pred` returns `true`, advancing `*index` past the kept key-values. The
+    // data is not moved inline, callers do that once they are done.
+    pub(crate) fn extract_next<F>(&mut self, index: &mut usize, pred: &mut F) -> Option<(K, V)>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        while *index < self.len() {
+            let remove = match &mut self.data {
+                MapData::Inline(vec) => {
+                    let (k, v) = &mut vec[*index];
+                    pred(k, v)
+                }
+                MapData::Heap(map) => {
+                    let (k, v) = map.get_index_mut(*index).unwrap();
+                    pred(k, v)
+                }
+            };
+            if remove {
+                return match &mut self.data {
+                    MapData::Inline(vec) => Some(vec.remove(*index)),
+                    MapData::Heap(map) => map.shift_remove_index(*index),
+                };
+            }
+            *index += 1;
+        }
+        None
+    }
+
+    /// Split the map into parallel key and value buffers, in insertion order.
+    pub fn into_columns(self) -> (SmallVec<[K; C]>, SmallVec<[V; C]>) {
+        self.into_iter().unzip()
+    }
+
+    /// Convert the map into a `Vec` of key-values, in insertion order.
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        match self.data {
+            MapData::Inline(vec) => vec.into_vec(),
+            MapData::Heap(map) => map.into_iter().collect(),
+        }
+    }
+
+    /// Clone the key-values into a `Vec` sorted by key, leaving the map
+    /// unchanged.
+    ///
+    /// Computational complexity: O(n log n)
+    pub fn to_sorted_vec(&self) -> Vec<(K, V)>
+    where
+        K: Ord + Clone,
+        V: Clone,
+    {
+        let mut vec: Vec<_> = self.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        // Keys are unique, so an unstable sort gives the same order.
+        vec.sort_unstable_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        vec
+    }
+
     // Helper method for macro, don't use directly.
     #[doc(hidden)]
     pub const fn from_const_unchecked_with_hasher(inline: SmallVec<[(K, V); C]>) -> Self {
-        Self {
-            data: MapData::Inline(inline),
-        }
+        Self::from_data(MapData::Inline(inline))
     }
 }
 
@@ -146,10 +630,12 @@ where
     /// Computational complexity:
     ///  - inline: O(n)
     ///  - heap: O(1)
+    #[inline]
     pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
     where
         Q: Hash + Equivalent<K>,
     {
+        self.record_inline_scan();
         match &self.data {
             MapData::Inline(vec) => vec
                 .iter()
@@ -165,10 +651,12 @@ where
     /// Computational complexity:
     ///  - inline: O(n)
     ///  - heap: O(1)
+    #[inline]
     pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
     where
         Q: Hash + Equivalent<K>,
     {
+        self.record_inline_scan();
         match &mut self.data {
             MapData::Inline(vec) => vec
                 .iter_mut()
@@ -178,9 +666,80 @@ where
         }
     }
 
+    /// Replace the value stored for `key` with `value`, if it is present, and
+    /// return the old value as `Ok`. If `key` is not present, the map is left
+    /// unchanged and `value` is returned as `Err`.
+    ///
+    /// Unlike [`SmallMap::insert`], this never adds a new key.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn replace_value<Q: ?Sized>(&mut self, key: &Q, value: V) -> Result<V, V>
+    where
+        Q: Hash + Equivalent<K>,
+    {
+        match self.get_mut(key) {
+            Some(existing) => Ok(mem::replace(existing, value)),
+            None => Err(value),
+        }
+    }
+
+    /// Return a reference to the key-value pair stored for `key`, if it is
+    /// present, else `None`.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn get_key_value<Q: ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        Q: Hash + Equivalent<K>,
+    {
+        self.get_full(key).map(|(_i, k, v)| (k, v))
+    }
+
+    /// Returns an iterator over the key-values of this map in order, each with
+    /// the value stored for the same key in `other`, if any.
+    ///
+    /// Computational complexity: one lookup in `other` per key-value
+    pub fn iter_zip<'a, const C2: usize, S2>(
+        &'a self,
+        other: &'a SmallMap<K, V, C2, S2>,
+    ) -> IterZip<'a, K, V, C2, S2>
+    where
+        S2: BuildHasher,
+    {
+        IterZip {
+            iter: self.iter(),
+            other,
+        }
+    }
+
+    /// Return the index and a reference to the key-value pair stored for
+    /// `key`, if it is present, else `None`.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn get_full<Q: ?Sized>(&self, key: &Q) -> Option<(usize, &K, &V)>
+    where
+        Q: Hash + Equivalent<K>,
+    {
+        self.record_inline_scan();
+        match &self.data {
+            MapData::Inline(vec) => vec
+                .iter()
+                .enumerate()
+                .find(|(_i, (k, _v))| key.equivalent(k))
+                .map(|(i, (k, v))| (i, k, v)),
+            MapData::Heap(map) => map.get_full(key),
+        }
+    }
+
     /// Get a key-value pair by index, if it is present, else `None`.
     ///
     /// Computational complexity: O(1)
+    #[inline]
     pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
         match &self.data {
             MapData::Inline(vec) => {
@@ -194,6 +753,15 @@ where
         }
     }
 
+    // Like `get_index_mut`, but with a mutable key. Only used by the raw entry
+    // API, which leaves keeping the keys consistent to the caller.
+    fn get_index_mut_with_key(&mut self, index: usize) -> Option<(&mut K, &mut V)> {
+        match &mut self.data {
+            MapData::Inline(vec) => vec.get_mut(index).map(|(k, v)| (k, v)),
+            MapData::Heap(map) => map.get_index_mut2(index),
+        }
+    }
+
     /// Get a mutable key-value pair by index, if it is present, else `None`.
     ///
     /// Computational complexity: O(1)
@@ -214,40 +782,192 @@ where
         }
     }
 
-    /// Return the item index, if it exists in the map, else `None`.
+    /// Returns mutable references to the values of `N` distinct keys at once.
     ///
-    /// Computational complexity:
-    ///  - inline: O(n)
-    ///  - heap: O(1)
-    pub fn get_index_of<Q: ?Sized>(&self, key: &Q) -> Option<usize>
+    /// Returns `None` if any of the keys is not present, or if any two keys
+    /// are equal.
+    ///
+    /// Computational complexity: O(n * N)
+    pub fn get_disjoint_mut<Q: ?Sized, const N: usize>(
+        &mut self,
+        keys: [&Q; N],
+    ) -> Option<[&mut V; N]>
     where
         Q: Hash + Equivalent<K>,
     {
-        match &self.data {
-            MapData::Inline(vec) => vec.iter().position(|(k, _v)| key.equivalent(k)),
-            MapData::Heap(map) => map.get_index_of(key),
+        let mut indices = [0; N];
+        for (i, key) in keys.into_iter().enumerate() {
+            let index = self.get_index_of(key)?;
+            if indices[..i].contains(&index) {
+                return None;
+            }
+            indices[i] = index;
         }
-    }
 
-    /// Get the given key's corresponding entry in the map for insertion and/or
-    /// in-place manipulation.
-    ///
-    /// Computational complexity:
-    ///  - inline: O(n)
-    ///  - heap: O(1)
-    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, C, S> {
-        let index = self.get_index_of(&key);
-        match index {
-            Some(index) => Entry::Occupied(self, index),
-            None => Entry::Vacant(self, key),
+        let mut values: [Option<&mut V>; N] = core::array::from_fn(|_| None);
+        for (index, value) in self.values_mut().enumerate() {
+            if let Some(i) = indices.iter().position(|&j| j == index) {
+                values[i] = Some(value);
+            }
         }
+        Some(values.map(|value| value.expect("every index was found")))
     }
 
-    /// Return `true` if an equivalent to `key` exists in the map.
+    /// Returns mutable references to the values of two distinct keys at once.
+    ///
+    /// Returns `None` if either key is not present, or if the keys are equal.
     ///
     /// Computational complexity:
     ///  - inline: O(n)
     ///  - heap: O(1)
+    pub fn get_pair_mut<Q: ?Sized>(&mut self, a: &Q, b: &Q) -> Option<(&mut V, &mut V)>
+    where
+        Q: Hash + Equivalent<K>,
+    {
+        let a = self.get_index_of(a)?;
+        let b = self.get_index_of(b)?;
+        if a == b {
+            return None;
+        }
+        let (low, high) = (a.min(b), a.max(b));
+        let (first, second) = match &mut self.data {
+            MapData::Inline(vec) => {
+                let (left, right) = vec.split_at_mut(high);
+                (&mut left[low].1, &mut right[0].1)
+            }
+            MapData::Heap(map) => {
+                let (left, right) = map.as_mut_slice().split_at_mut(high);
+                (left.get_index_mut(low)?.1, right.get_index_mut(0)?.1)
+            }
+        };
+        Some(if a < b {
+            (first, second)
+        } else {
+            (second, first)
+        })
+    }
+
+    /// Swaps the values stored for the keys `a` and `b`, leaving the keys in
+    /// place. Returns `false`, without changing the map, if either key is not
+    /// present.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn swap_values<Q: ?Sized>(&mut self, a: &Q, b: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K>,
+    {
+        if let Some((a, b)) = self.get_pair_mut(a, b) {
+            mem::swap(a, b);
+            true
+        } else {
+            // Equal keys have nothing to swap.
+            self.contains_key(a) && self.contains_key(b)
+        }
+    }
+
+    /// Return the item index, if it exists in the map, else `None`.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    #[inline]
+    pub fn get_index_of<Q: ?Sized>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K>,
+    {
+        self.record_inline_scan();
+        match &self.data {
+            MapData::Inline(vec) => vec.iter().position(|(k, _v)| key.equivalent(k)),
+            MapData::Heap(map) => map.get_index_of(key),
+        }
+    }
+
+    /// Get the given key's corresponding entry in the map for insertion and/or
+    /// in-place manipulation.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    #[inline]
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, C, S> {
+        match self.get_index_of(&key) {
+            Some(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            None => Entry::Vacant(VacantEntry { map: self, key }),
+        }
+    }
+
+    /// Get the given borrowed key's corresponding entry in the map for
+    /// insertion and/or in-place manipulation.
+    ///
+    /// Unlike [`SmallMap::entry`], the owned key is only created, using
+    /// [`ToOwned`], when a value is inserted into a vacant entry.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn entry_ref<'b, Q: ?Sized>(&mut self, key: &'b Q) -> EntryRef<'_, 'b, K, Q, V, C, S>
+    where
+        Q: Hash + Equivalent<K>,
+    {
+        match self.get_index_of(key) {
+            Some(index) => EntryRef::Occupied(OccupiedEntry { map: self, index }),
+            None => EntryRef::Vacant(VacantEntryRef { map: self, key }),
+        }
+    }
+
+    /// Creates a raw entry builder, which finds a key-value by key or by a
+    /// predicate and allows editing its key in place.
+    ///
+    /// The raw entry API leaves some invariants to the caller, see
+    /// [`RawEntryBuilderMut`].
+    pub fn raw_entry_mut(&mut self) -> RawEntryBuilderMut<'_, K, V, C, S> {
+        RawEntryBuilderMut { map: self }
+    }
+
+    /// Get the entry of the key-value at `index` for in-place manipulation, or
+    /// `None` if `index` is out of bounds.
+    ///
+    /// Computational complexity: O(1)
+    pub fn get_index_entry(&mut self, index: usize) -> Option<OccupiedEntry<'_, K, V, C, S>> {
+        if index < self.len() {
+            Some(OccupiedEntry { map: self, index })
+        } else {
+            None
+        }
+    }
+
+    /// Get the entry of the first key-value in insertion order for in-place
+    /// manipulation, or `None` if the map is empty.
+    ///
+    /// Computational complexity: O(1)
+    pub fn first_entry(&mut self) -> Option<OccupiedEntry<'_, K, V, C, S>> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(OccupiedEntry {
+                map: self,
+                index: 0,
+            })
+        }
+    }
+
+    /// Get the entry of the last key-value in insertion order for in-place
+    /// manipulation, or `None` if the map is empty.
+    ///
+    /// Computational complexity: O(1)
+    pub fn last_entry(&mut self) -> Option<OccupiedEntry<'_, K, V, C, S>> {
+        let index = self.len().checked_sub(1)?;
+        Some(OccupiedEntry { map: self, index })
+    }
+
+    /// Return `true` if an equivalent to `key` exists in the map.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    #[inline]
     pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
     where
         Q: Hash + Equivalent<K>,
@@ -260,15 +980,57 @@ where
     /// If the map len is smaller or equal the inline capacity, the data will be
     /// moved inline.
     pub fn from_map(map: IndexMap<K, V, S>) -> Self {
-        if map.len() <= C {
-            Self {
-                data: MapData::Inline(SmallVec::from_vec(map.into_iter().collect())),
-            }
+        if Self::fits_inline(map.len()) {
+            Self::from_data(MapData::Inline(SmallVec::from_vec(
+                map.into_iter().collect(),
+            )))
         } else {
-            Self {
-                data: MapData::Heap(map),
-            }
+            Self::from_data(MapData::Heap(Box::new(map)))
+        }
+    }
+
+    /// Convert the specified vec of key-value pairs into a `SmallMap`, keeping
+    /// the first occurrence of duplicate keys.
+    ///
+    /// If the number of unique keys exceeds the inline capacity, the data will
+    /// be moved to the heap.
+    pub fn from_vec_dedup(vec: SmallVec<[(K, V); C]>) -> Self
+    where
+        S: Default,
+    {
+        Self::from_iter_first_wins(vec)
+    }
+
+    /// Create a map from key-values that may contain duplicate keys, keeping
+    /// the first occurrence of each key.
+    ///
+    /// If the number of unique keys exceeds the inline capacity, the data will
+    /// be moved to the heap.
+    pub fn from_iter_first_wins<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        S: Default,
+    {
+        let mut map = Self::default();
+        for (key, value) in iter {
+            map.entry(key).or_insert(value);
         }
+        map
+    }
+
+    /// Create a map from key-values that may contain duplicate keys, keeping
+    /// the value of the last occurrence of each key at the position of its
+    /// first occurrence, like repeated calls to [`SmallMap::insert`]. This is
+    /// the same as collecting the key-values.
+    ///
+    /// If the number of unique keys exceeds the inline capacity, the data will
+    /// be moved to the heap.
+    pub fn from_iter_last_wins<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        S: Default,
+    {
+        iter.into_iter().collect()
     }
 
     /// Remove the key-value pair equivalent to `key` and return its value.
@@ -276,8 +1038,8 @@ where
     /// If `key` is not present `None` is returned.
     ///
     /// If an existing key is removed that causes the size of the `SmallMap` to
-    /// be equal to or below the inline capacity, all remaining data after
-    /// removal of the specified key-value pair is moved to the heap.
+    /// drop to the downgrade threshold, all remaining data after removal of the
+    /// specified key-value pair is moved inline.
     ///
     /// The behavior of this method is equivalent to `.swap_remove(key)` on
     /// `HashMap`s and `Vec`s, order is not preserved.
@@ -298,8 +1060,8 @@ where
     /// If `key` is not present `None` is returned.
     ///
     /// If an existing key is removed that causes the size of the `SmallMap` to
-    /// be equal to or below the inline capacity, all remaining data after
-    /// removal of the specified key-value pair is moved to the heap.
+    /// drop to the downgrade threshold, all remaining data after removal of the
+    /// specified key-value pair is moved inline.
     ///
     /// The behavior of this method is equivalent to `.swap_remove(key)` on
     /// `HashMap`s and `Vec`s, order is not preserved.
@@ -311,6 +1073,7 @@ where
     where
         Q: Hash + Equivalent<K>,
     {
+        self.record_inline_scan();
         match &mut self.data {
             MapData::Inline(vec) => {
                 let index = vec.iter().position(|(k, _v)| key.equivalent(k));
@@ -320,14 +1083,313 @@ where
             }
             MapData::Heap(map) => {
                 let value = map.swap_remove_full(key);
-                if value.is_some() && map.len() <= C {
-                    self.data = MapData::Inline(map.drain(0..map.len()).collect());
+                if value.is_some() {
+                    self.downgrade_if_small();
                 }
                 value
             }
         }
     }
 
+    /// Remove the key-value pair equivalent to `key` and return it.
+    ///
+    /// If `key` is not present `None` is returned.
+    ///
+    /// Like [`SmallMap::remove`], the last key-value takes the place of the
+    /// removed one, so order is not preserved, and the remaining data is moved
+    /// inline if it fits the downgrade threshold.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn swap_remove_entry<Q: ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        Q: Hash + Equivalent<K>,
+    {
+        self.swap_remove_full(key).map(|(_, k, v)| (k, v))
+    }
+
+    /// Remove the key-value pair equivalent to `key` and return it, shifting
+    /// all following key-values to preserve order.
+    ///
+    /// If `key` is not present `None` is returned. The remaining data is moved
+    /// inline if it fits the downgrade threshold.
+    ///
+    /// Computational complexity: O(n)
+    pub fn shift_remove_entry<Q: ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        Q: Hash + Equivalent<K>,
+    {
+        self.record_inline_scan();
+        match &mut self.data {
+            MapData::Inline(vec) => {
+                let index = vec.iter().position(|(k, _v)| key.equivalent(k))?;
+                Some(vec.remove(index))
+            }
+            MapData::Heap(map) => {
+                let entry = map.shift_remove_entry(key);
+                if entry.is_some() {
+                    self.downgrade_if_small();
+                }
+                entry
+            }
+        }
+    }
+
+    /// Removes the last key-value in insertion order and returns it, or `None`
+    /// if the map is empty.
+    ///
+    /// If the removal causes the size of the `SmallMap` to drop to the
+    /// downgrade threshold, all remaining data is moved inline.
+    ///
+    /// Computational complexity: O(1)
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        match &mut self.data {
+            MapData::Inline(vec) => vec.pop(),
+            MapData::Heap(map) => {
+                let entry = map.pop();
+                self.downgrade_if_small();
+                entry
+            }
+        }
+    }
+
+    /// Removes the key-value at `index`, shifting all following key-values to
+    /// preserve order, and returns it together with the new length of the
+    /// map, or `None` if `index` is out of bounds.
+    ///
+    /// If the removal causes the size of the `SmallMap` to drop to the
+    /// downgrade threshold, all remaining data is moved inline.
+    ///
+    /// Computational complexity: O(n)
+    pub fn pop_index(&mut self, index: usize) -> Option<((K, V), usize)> {
+        let entry = self.get_index_entry(index)?.shift_remove_entry();
+        Some((entry, self.len()))
+    }
+
+    /// Splits the map into two at the given index. Returns a new map
+    /// containing the key-values from `at` onwards, and leaves the key-values
+    /// before `at` in `self`. Order is preserved in both maps.
+    ///
+    /// The returned map is stored inline if its length is within the inline
+    /// capacity, and on the heap otherwise, and uses the same downgrade
+    /// threshold as `self`. Like other removals, `self` is moved inline if the
+    /// remaining data fits the downgrade threshold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    ///
+    /// Computational complexity: O(n)
+    pub fn split_off(&mut self, at: usize) -> Self
+    where
+        S: Clone,
+    {
+        let mut other = match &mut self.data {
+            MapData::Inline(vec) => {
+                assert!(at <= vec.len(), "index out of bounds");
+                Self::from_data(MapData::Inline(vec.drain(at..).collect()))
+            }
+            MapData::Heap(map) => {
+                let other = map.split_off(at);
+                self.downgrade_if_small();
+                Self::from_map(other)
+            }
+        };
+        other.downgrade_below = self.downgrade_below;
+        other
+    }
+
+    /// Retains only the key-values for which `keep` returns `true`, in order,
+    /// and removes all others.
+    ///
+    /// If the remaining data fits the downgrade threshold, it is moved inline.
+    ///
+    /// Computational complexity: O(n)
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        match &mut self.data {
+            MapData::Inline(vec) => vec.retain(|(k, v)| keep(k, v)),
+            MapData::Heap(map) => {
+                map.retain(|k, v| keep(k, v));
+                self.downgrade_if_small();
+            }
+        }
+    }
+
+    /// Like [`SmallMap::retain`], and returns the number of removed
+    /// key-values.
+    ///
+    /// Computational complexity: O(n)
+    pub fn retain_count<F>(&mut self, keep: F) -> usize
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let len = self.len();
+        self.retain(keep);
+        len - self.len()
+    }
+
+    /// Removes the key-values for all of `keys` that are present, keeping the
+    /// order of the remaining key-values.
+    ///
+    /// If the remaining data fits the downgrade threshold, it is moved inline.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n) per key
+    ///  - heap: O(n + k), where k is the number of keys
+    pub fn remove_all<I: IntoIterator<Item = K>>(&mut self, keys: I) {
+        match &mut self.data {
+            MapData::Inline(vec) => {
+                for key in keys {
+                    if let Some(index) = vec.iter().position(|(k, _v)| &key == k) {
+                        vec.remove(index);
+                    }
+                }
+            }
+            MapData::Heap(map) => {
+                let mut remove: Vec<bool> = Vec::new();
+                for key in keys {
+                    if let Some(index) = map.get_index_of(&key) {
+                        if remove.is_empty() {
+                            remove.resize(map.len(), false);
+                        }
+                        remove[index] = true;
+                    }
+                }
+                if !remove.is_empty() {
+                    let mut remove = remove.into_iter();
+                    map.retain(|_k, _v| !remove.next().unwrap_or(false));
+                    self.downgrade_if_small();
+                }
+            }
+        }
+    }
+
+    /// Shrinks the capacity of the map as much as possible.
+    ///
+    /// If the map is on the heap and its data fits the downgrade threshold,
+    /// the data is moved inline.
+    pub fn shrink_to_fit(&mut self) {
+        match &mut self.data {
+            MapData::Inline(vec) => vec.shrink_to_fit(),
+            MapData::Heap(map) => {
+                map.shrink_to_fit();
+                self.downgrade_if_small();
+            }
+        }
+    }
+
+    /// Shrinks the capacity of the map with a lower limit. It will drop down no
+    /// lower than the supplied limit while maintaining the internal rules and
+    /// possibly leaving some space in accordance with the resize policy.
+    ///
+    /// If the map is on the heap, its data fits the inline capacity and
+    /// `min_capacity` does not exceed it, the data is moved inline regardless
+    /// of the downgrade threshold.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        match &mut self.data {
+            MapData::Inline(_) => {}
+            MapData::Heap(map) if min_capacity <= C && Self::fits_inline(map.len()) => {
+                self.data = MapData::Inline(map.drain(..).collect());
+            }
+            MapData::Heap(map) => map.shrink_to(min_capacity),
+        }
+    }
+
+    /// Moves the data into its smallest representation: inline if it fits the
+    /// inline capacity, otherwise on the heap with the capacity shrunk as much
+    /// as possible.
+    ///
+    /// Unlike [`SmallMap::shrink_to_fit`], this moves the data inline whenever
+    /// it fits, regardless of the downgrade threshold.
+    ///
+    /// Computational complexity: O(n)
+    pub fn compact(&mut self) {
+        match &mut self.data {
+            MapData::Inline(vec) => vec.shrink_to_fit(),
+            MapData::Heap(map) if Self::fits_inline(map.len()) => {
+                self.data = MapData::Inline(map.drain(..).collect());
+            }
+            MapData::Heap(map) => map.shrink_to_fit(),
+        }
+    }
+
+    /// Sorts the map by key, in place.
+    ///
+    /// The sort is stable. Positional indices, such as those returned by
+    /// [`get_index_of`](Self::get_index_of), reflect the new order.
+    pub fn sort_keys(&mut self)
+    where
+        K: Ord,
+    {
+        match &mut self.data {
+            MapData::Inline(vec) => vec.sort_by(|(k1, _), (k2, _)| k1.cmp(k2)),
+            MapData::Heap(map) => map.sort_keys(),
+        }
+    }
+
+    /// Sorts the map in place using the comparison function `cmp`, which is
+    /// passed the key-values of two entries.
+    ///
+    /// The sort is stable. Positional indices reflect the new order.
+    pub fn sort_by<F>(&mut self, mut cmp: F)
+    where
+        F: FnMut(&K, &V, &K, &V) -> Ordering,
+    {
+        match &mut self.data {
+            MapData::Inline(vec) => vec.sort_by(|(k1, v1), (k2, v2)| cmp(k1, v1, k2, v2)),
+            MapData::Heap(map) => map.sort_by(cmp),
+        }
+    }
+
+    /// Sorts the map in place using the comparison function `cmp`, which is
+    /// passed the key-values of two entries.
+    ///
+    /// The sort is unstable. Positional indices reflect the new order.
+    pub fn sort_unstable_by<F>(&mut self, mut cmp: F)
+    where
+        F: FnMut(&K, &V, &K, &V) -> Ordering,
+    {
+        match &mut self.data {
+            MapData::Inline(vec) => {
+                vec.sort_unstable_by(|(k1, v1), (k2, v2)| cmp(k1, v1, k2, v2));
+            }
+            MapData::Heap(map) => map.sort_unstable_by(cmp),
+        }
+    }
+
+    /// Reverses the order of the key-values in place.
+    ///
+    /// Computational complexity: O(n)
+    pub fn reverse(&mut self) {
+        match &mut self.data {
+            MapData::Inline(vec) => vec.reverse(),
+            MapData::Heap(map) => map.reverse(),
+        }
+    }
+
+    /// Binary searches this map for `key`, assuming the map is sorted by key,
+    /// for example with [`sort_keys`](Self::sort_keys).
+    ///
+    /// If the key is found then [`Result::Ok`] is returned, containing its
+    /// index. Otherwise [`Result::Err`] is returned, containing the index
+    /// where the key could be inserted while maintaining sorted order. If the
+    /// map is not sorted, the result is unspecified.
+    ///
+    /// Computational complexity: O(log n)
+    pub fn binary_search_keys(&self, key: &K) -> Result<usize, usize>
+    where
+        K: Ord,
+    {
+        match &self.data {
+            MapData::Inline(vec) => vec.binary_search_by(|(k, _)| k.cmp(key)),
+            MapData::Heap(map) => map.binary_search_keys(key),
+        }
+    }
+
     /// Binary searches this map with a comparator function.
     ///
     /// The comparator function should implement an order consistent with the
@@ -383,6 +1445,7 @@ where
     /// Computational complexity:
     ///  - inline: O(n)
     ///  - heap: O(1)
+    #[inline]
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
         self.insert_full(key, value).1
     }
@@ -401,7 +1464,9 @@ where
     /// Computational complexity:
     ///  - inline: O(n)
     ///  - heap: O(1)
+    #[inline]
     pub fn insert_full(&mut self, key: K, value: V) -> (usize, Option<V>) {
+        self.record_inline_scan();
         match &mut self.data {
             MapData::Inline(sv) => {
                 let existing_index = sv.iter().position(|(k, _v)| &key == k);
@@ -409,10 +1474,15 @@ where
                     let ret = mem::replace(&mut sv[existing_index], (key, value));
                     (existing_index, Some(ret.1))
                 } else if sv.len() + 1 > C {
-                    // Move to heap
-                    let mut map = sv.drain(0..sv.len()).collect::<IndexMap<_, _, _>>();
+                    // Move to heap. The `IndexMap` is allocated once with room
+                    // for the drained key-values and the new one, so inserting
+                    // the new key-value does not grow it again. See the
+                    // `spill` benchmarks.
+                    let mut map = IndexMap::with_capacity_and_hasher(sv.len() + 1, S::default());
+                    map.extend(sv.drain(0..sv.len()));
                     let ret = map.insert_full(key, value);
-                    self.data = MapData::Heap(map);
+                    self.data = MapData::Heap(Box::new(map));
+                    self.record_spill();
                     ret
                 } else {
                     sv.push((key, value));
@@ -422,22 +1492,505 @@ where
             MapData::Heap(map) => map.insert_full(key, value),
         }
     }
-}
 
-impl<K, V, const C: usize, S> Default for SmallMap<K, V, C, S> {
-    fn default() -> Self {
-        Self {
-            data: Default::default(),
+    // Returns the index of the key equivalent to `key`, or inserts the
+    // key-value returned by `f` and returns its index. Inline, the new
+    // key-value is pushed without scanning the keys again. The key returned by
+    // `f` must be equivalent to `key`. Used by `SmallSet::get_or_insert_with`.
+    #[cfg(feature = "smallset")]
+    pub(crate) fn get_index_of_or_insert_with<Q: ?Sized, F>(&mut self, key: &Q, f: F) -> usize
+    where
+        Q: Hash + Equivalent<K>,
+        F: FnOnce(&Q) -> (K, V),
+    {
+        if let Some(index) = self.get_index_of(key) {
+            return index;
+        }
+        let (new_key, value) = f(key);
+        debug_assert!(
+            key.equivalent(&new_key),
+            "the inserted value must be equivalent to the lookup value"
+        );
+        match &mut self.data {
+            MapData::Inline(vec) if vec.len() < C => {
+                vec.push((new_key, value));
+                vec.len() - 1
+            }
+            _ => self.insert_full(new_key, value).0,
         }
     }
-}
 
-impl<K, V, const C: usize, S> Hash for SmallMap<K, V, C, S>
-where
-    K: Hash + Eq,
-    V: Hash + Eq,
-{
-    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+    // Replaces the stored key equivalent to `key` with `key` and returns the
+    // replaced key, keeping the stored value. If there is no such key, `key` is
+    // inserted with `value`. Used by `SmallSet::replace`.
+    #[cfg(feature = "smallset")]
+    pub(crate) fn replace_key(&mut self, key: K, value: V) -> Option<K> {
+        self.record_inline_scan();
+        let stored = match &mut self.data {
+            MapData::Inline(vec) => vec.iter_mut().find(|(k, _v)| k == &key).map(|(k, _v)| k),
+            MapData::Heap(map) => map.get_full_mut2(&key).map(|(_i, k, _v)| k),
+        };
+        match stored {
+            Some(stored) => Some(mem::replace(stored, key)),
+            None => {
+                self.insert(key, value);
+                None
+            }
+        }
+    }
+
+    // Moves all key-values into a new map with the default downgrade threshold
+    // and leaves `self` empty like after `clear`, keeping its own threshold.
+    // Used by `SmallSet::drain`.
+    #[cfg(feature = "smallset")]
+    pub(crate) fn take_data(&mut self) -> Self {
+        let empty = match self.data {
+            // Without inline capacity this is always `0`.
+            MapData::Heap(_) if self.downgrade_below == 0 => {
+                MapData::Heap(Box::new(IndexMap::with_hasher(S::default())))
+            }
+            _ => MapData::Inline(SmallVec::new()),
+        };
+        Self::from_data(mem::replace(&mut self.data, empty))
+    }
+
+    /// Return a mutable reference to the value stored for `key`, inserting the
+    /// value returned by `f` if the key is not present.
+    ///
+    /// `f` is only called if the key is not present. If the insertion causes
+    /// the size of the `SmallMap` to exceed the inline capacity, all data is
+    /// moved to the heap first, and the returned reference points to the heap.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn get_or_insert_with<F>(&mut self, key: K, f: F) -> &mut V
+    where
+        F: FnOnce() -> V,
+    {
+        self.entry(key).or_insert_with_key_and_index(|_, _| f())
+    }
+
+    /// Return a mutable reference to the value stored for `key`, inserting the
+    /// value returned by `f` if the key is not present.
+    ///
+    /// `f` is only called if the key is not present. If it returns an error,
+    /// the map is left unchanged and the error is returned.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn get_or_try_insert_with<F, E>(&mut self, key: K, f: F) -> Result<&mut V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+    {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(f()?)),
+        }
+    }
+
+    /// Inserts a key-value pair at position `index`, shifting all key-values
+    /// after it.
+    ///
+    /// If an equivalent key already exists in the map, its value is replaced
+    /// and the key-value is moved to `index`. The old value is returned.
+    /// Otherwise the new key-value is inserted at `index` and `None` is
+    /// returned.
+    ///
+    /// If a new key is added that causes the size of the `SmallMap` to exceed
+    /// the inline capacity, all existing data and the new key-value is moved
+    /// to the heap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds. Valid indices are `0..=len` when
+    /// inserting a new key, and `0..len` when moving an existing key.
+    ///
+    /// Computational complexity: O(n)
+    pub fn shift_insert(&mut self, index: usize, key: K, value: V) -> Option<V> {
+        let len = self.len();
+        let valid = if self.contains_key(&key) {
+            index < len
+        } else {
+            index <= len
+        };
+        assert!(valid, "index out of bounds");
+
+        let (from, old) = self.insert_full(key, value);
+        self.move_index(from, index);
+        old
+    }
+
+    /// Inserts a key-value pair at its sorted position by key, and returns its
+    /// index and the old value, if any.
+    ///
+    /// The map must already be sorted by key, for example by only inserting
+    /// with this method or by calling [`SmallMap::sort_keys`] first. Then
+    /// [`SmallMap::binary_search_keys`] stays valid. If an equivalent key
+    /// already exists, its value is replaced in place, even if the map was not
+    /// sorted and the key is not at its sorted position.
+    ///
+    /// If a new key is added that causes the size of the `SmallMap` to exceed
+    /// the inline capacity, all existing data and the new key-value is moved
+    /// to the heap.
+    ///
+    /// Computational complexity: O(n)
+    pub fn insert_sorted(&mut self, key: K, value: V) -> (usize, Option<V>)
+    where
+        K: Ord,
+    {
+        match self.binary_search_keys(&key) {
+            Ok(index) => {
+                let old = mem::replace(self.get_index_mut(index).unwrap().1, value);
+                (index, Some(old))
+            }
+            Err(index) => match self.insert_full(key, value) {
+                // The binary search missed an equivalent key, so the map was
+                // not sorted. Its value was replaced in place.
+                (from, Some(old)) => (from, Some(old)),
+                (from, None) => {
+                    self.move_index(from, index);
+                    (index, None)
+                }
+            },
+        }
+    }
+
+    /// Inserts a key-value pair while keeping at most `max` key-values, and
+    /// returns the evicted key-value, if any.
+    ///
+    /// If an equivalent key already exists, its value is replaced in place and
+    /// nothing is evicted. Otherwise, if the map already holds `max` key-values,
+    /// the oldest one, the first in insertion order, is removed and returned
+    /// before the new key-value is added at the end. If `max` is `0`, the new
+    /// key-value itself is returned.
+    ///
+    /// At most one key-value is evicted, so the map must not already hold more
+    /// than `max` key-values. This is checked in debug builds. Use
+    /// [`SmallMap::truncate`] or [`SmallMap::drain_range`] first to lower the
+    /// length of a larger map.
+    ///
+    /// Computational complexity: O(n) when evicting, otherwise like
+    /// [`SmallMap::insert`].
+    pub fn insert_bounded(&mut self, max: usize, key: K, value: V) -> Option<(K, V)> {
+        debug_assert!(
+            self.len() <= max,
+            "insert_bounded called on a map holding more than `max` key-values"
+        );
+        if max == 0 {
+            return Some((key, value));
+        }
+        if let Some(existing) = self.get_mut(&key) {
+            *existing = value;
+            return None;
+        }
+        let evicted = if self.len() >= max {
+            self.first_entry().map(OccupiedEntry::shift_remove_entry)
+        } else {
+            None
+        };
+        self.insert(key, value);
+        evicted
+    }
+
+    /// Inserts a key-value pair without allocating, returning the previous
+    /// value for `key`, if any.
+    ///
+    /// If an equivalent key already exists, its value is replaced. A new key
+    /// is only added if there is room for it, inline or in the already
+    /// allocated heap capacity. Otherwise the map is left unchanged and the
+    /// key-value is returned in `Err`, instead of moving the data to the heap
+    /// or growing it.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn checked_insert(&mut self, key: K, value: V) -> Result<Option<V>, (K, V)> {
+        self.record_inline_scan();
+        match &mut self.data {
+            MapData::Inline(sv) => {
+                if let Some((_k, v)) = sv.iter_mut().find(|(k, _v)| &key == k) {
+                    Ok(Some(mem::replace(v, value)))
+                } else if sv.len() < C {
+                    sv.push((key, value));
+                    Ok(None)
+                } else {
+                    Err((key, value))
+                }
+            }
+            MapData::Heap(map) => {
+                if let Some(v) = map.get_mut(&key) {
+                    Ok(Some(mem::replace(v, value)))
+                } else if map.len() < map.capacity() {
+                    Ok(map.insert(key, value))
+                } else {
+                    Err((key, value))
+                }
+            }
+        }
+    }
+
+    /// Reserves capacity for at least `additional` more key-values.
+    ///
+    /// If the key-values fit in the inline capacity, this does nothing.
+    /// Otherwise the data is moved to the heap, or the heap capacity is
+    /// increased.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    pub fn reserve(&mut self, additional: usize) {
+        match &mut self.data {
+            MapData::Inline(sv) if sv.len().saturating_add(additional) > C => {
+                let mut map = IndexMap::with_hasher(S::default());
+                map.reserve(sv.len().saturating_add(additional));
+                map.extend(sv.drain(0..sv.len()));
+                self.data = MapData::Heap(Box::new(map));
+                self.record_spill();
+            }
+            MapData::Inline(_) => {}
+            MapData::Heap(map) => map.reserve(additional),
+        }
+    }
+
+    /// Reserves capacity for exactly `additional` more key-values, without
+    /// the amortized over-allocation of [`SmallMap::reserve`].
+    ///
+    /// If the key-values fit in the inline capacity, this does nothing.
+    /// Otherwise the data is moved to the heap, or the heap capacity is
+    /// increased.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        match &mut self.data {
+            MapData::Inline(sv) if sv.len().saturating_add(additional) > C => {
+                let mut map = IndexMap::with_hasher(S::default());
+                map.reserve_exact(sv.len().saturating_add(additional));
+                map.extend(sv.drain(0..sv.len()));
+                self.data = MapData::Heap(Box::new(map));
+                self.record_spill();
+            }
+            MapData::Inline(_) => {}
+            MapData::Heap(map) => map.reserve_exact(additional),
+        }
+    }
+
+    /// Tries to reserve capacity for at least `additional` more key-values.
+    ///
+    /// If the key-values fit in the inline capacity, this does nothing.
+    /// Otherwise the data is moved to the heap, or the heap capacity is
+    /// increased. If the allocation fails an error is returned, and the map is
+    /// left unchanged.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        match &mut self.data {
+            MapData::Inline(sv) if sv.len().saturating_add(additional) > C => {
+                let mut map = IndexMap::with_hasher(S::default());
+                map.try_reserve(sv.len().saturating_add(additional))?;
+                map.extend(sv.drain(0..sv.len()));
+                self.data = MapData::Heap(Box::new(map));
+                self.record_spill();
+                Ok(())
+            }
+            MapData::Inline(_) => Ok(()),
+            MapData::Heap(map) => map.try_reserve(additional),
+        }
+    }
+
+    /// Moves all key-values from `other` into `self`, leaving `other` empty.
+    ///
+    /// Key-values are inserted in the order of `other`. If a key already exists
+    /// in `self`, its value is replaced but its position is kept, like
+    /// [`SmallMap::insert`].
+    ///
+    /// If the combined number of key-values exceeds the inline capacity, the
+    /// data is moved to the heap once, with enough room for all of them.
+    pub fn append(&mut self, other: &mut Self) {
+        let additional = other.len();
+        match &mut self.data {
+            MapData::Inline(sv) if sv.len() + additional > C => {
+                let mut map =
+                    IndexMap::with_capacity_and_hasher(sv.len() + additional, S::default());
+                map.extend(sv.drain(0..sv.len()));
+                self.data = MapData::Heap(Box::new(map));
+                self.record_spill();
+            }
+            MapData::Inline(_) => {}
+            MapData::Heap(map) => map.reserve(additional),
+        }
+        for (key, value) in Self::from_data(mem::take(&mut other.data)) {
+            self.insert(key, value);
+        }
+        // Overlapping keys may leave fewer key-values than were reserved for.
+        self.downgrade_if_small();
+    }
+
+    /// Moves all key-values from `other` into `self`, in the order of `other`.
+    ///
+    /// A key-value whose key is absent from `self` is inserted, otherwise its
+    /// value is combined into the existing value with `combine`.
+    ///
+    /// ```
+    /// use more_collections::smallmap;
+    /// use more_collections::SmallMap;
+    ///
+    /// let mut counts: SmallMap<_, _, 4> = smallmap! { 'a' => 1, 'b' => 2 };
+    /// counts.merge_with(smallmap! { 'b' => 3, 'c' => 1 }, |count, other| *count += other);
+    /// assert!(counts.iter_eq([('a', 1), ('b', 5), ('c', 1)]));
+    /// ```
+    pub fn merge_with<F>(&mut self, other: Self, mut combine: F)
+    where
+        F: FnMut(&mut V, V),
+    {
+        for (key, value) in other {
+            match self.entry(key) {
+                Entry::Occupied(mut entry) => combine(entry.get_mut(), value),
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+    }
+
+    /// Adds `value` to the collection stored for `key`, inserting an empty
+    /// collection with `V::default()` first if the key is absent.
+    ///
+    /// ```
+    /// use more_collections::SmallMap;
+    ///
+    /// let mut groups: SmallMap<_, Vec<_>, 4> = SmallMap::new();
+    /// for word in ["apple", "bean", "avocado"] {
+    ///     groups.push_grouped(word.chars().next().unwrap(), word);
+    /// }
+    /// assert!(groups.iter_eq([('a', vec!["apple", "avocado"]), ('b', vec!["bean"])]));
+    /// ```
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn push_grouped<V2>(&mut self, key: K, value: V2)
+    where
+        V: Extend<V2> + Default,
+    {
+        match self.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(V::default()),
+        }
+        .extend(core::iter::once(value));
+    }
+
+    /// Rebuilds the map from the key-values returned by `f`, in order, dropping
+    /// key-values for which `f` returns `None`.
+    ///
+    /// Unlike [`SmallMap::retain`], `f` takes ownership of each key-value and
+    /// may change the key. If two returned keys collide, the later value
+    /// overwrites the earlier one, which keeps its position, like
+    /// [`SmallMap::insert`].
+    ///
+    /// The rebuilt map is stored inline if it fits the inline capacity, and on
+    /// the heap otherwise.
+    ///
+    /// Computational complexity: O(n)
+    pub fn map_retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(K, V) -> Option<(K, V)>,
+    {
+        let entries = Self::from_data(mem::take(&mut self.data));
+        let rebuilt: Self = entries.into_iter().filter_map(|(k, v)| f(k, v)).collect();
+        self.data = rebuilt.data;
+    }
+
+    /// Convert the map into an immutable [`FrozenSmallMap`] that stores the
+    /// key-values in a single boxed slice, in insertion order.
+    pub fn into_frozen(self) -> FrozenSmallMap<K, V, S> {
+        FrozenSmallMap::from_unique_entries(self.into_vec().into_boxed_slice(), S::default())
+    }
+
+    /// Convert the map into an `IndexMap`, in insertion order.
+    ///
+    /// If the data is stored inline, a new `IndexMap` is created with
+    /// `S::default()`.
+    pub fn into_index_map(self) -> IndexMap<K, V, S> {
+        match self.data {
+            MapData::Inline(vec) => vec.into_iter().collect(),
+            MapData::Heap(map) => *map,
+        }
+    }
+
+    // Creates a map from key-values whose keys are known to be unique,
+    // skipping the duplicate key checks of `insert`.
+    fn from_unique_iter<I>(iter: I) -> Self
+    where
+        I: ExactSizeIterator<Item = (K, V)>,
+    {
+        if Self::fits_inline(iter.len()) {
+            Self::from_data(MapData::Inline(iter.collect()))
+        } else {
+            let mut map = IndexMap::with_capacity_and_hasher(iter.len(), S::default());
+            map.extend(iter);
+            Self::from_data(MapData::Heap(Box::new(map)))
+        }
+    }
+
+    /// Create a map from parallel key and value buffers, the inverse of
+    /// [`SmallMap::into_columns`].
+    ///
+    /// Returns an error if the number of keys and values differ, or if any key
+    /// occurs more than once.
+    pub fn from_columns(
+        keys: SmallVec<[K; C]>,
+        values: SmallVec<[V; C]>,
+    ) -> Result<Self, FromColumnsError> {
+        if keys.len() != values.len() {
+            return Err(FromColumnsError::LengthMismatch);
+        }
+        Self::try_from(keys.into_iter().zip(values).collect::<SmallVec<_>>())
+            .map_err(|DuplicateKeyError| FromColumnsError::DuplicateKey)
+    }
+
+    /// Return the index of the key equivalent to `key`, or reserve a new slot
+    /// for it at the end of the map.
+    ///
+    /// Returns the index and `true` if a new slot was reserved, or `false` if
+    /// the key already existed. The owned key is only created, using
+    /// [`ToOwned`], when a new slot is reserved.
+    ///
+    /// A newly reserved slot holds `V::default()` as a placeholder until the
+    /// caller sets the actual value, e.g. with `map[index] = value`. Until
+    /// then, the map contains this placeholder like any other value.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn locate_or_reserve<Q: ?Sized>(&mut self, key: &Q) -> (usize, bool)
+    where
+        Q: Hash + Equivalent<K> + ToOwned<Owned = K>,
+        V: Default,
+    {
+        match self.get_index_of(key) {
+            Some(index) => (index, false),
+            None => (self.insert_full(key.to_owned(), V::default()).0, true),
+        }
+    }
+}
+
+impl<K, V, const C: usize, S> Default for SmallMap<K, V, C, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::from_data(Default::default())
+    }
+}
+
+impl<K, V, const C: usize, S> Hash for SmallMap<K, V, C, S>
+where
+    K: Hash + Eq,
+    V: Hash + Eq,
+{
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
         self.iter().for_each(|(k, v)| {
             k.hash(state);
             v.hash(state);
@@ -460,9 +2013,53 @@ where
     }
 }
 
-impl<K, V, const C: usize, S> Default for MapData<K, V, C, S> {
+/// Compares the key-values in insertion order with the key-values of the
+/// slice, so the order must match.
+impl<K, V, const C: usize, S> PartialEq<[(K, V)]> for SmallMap<K, V, C, S>
+where
+    K: PartialEq,
+    V: PartialEq,
+{
+    fn eq(&self, other: &[(K, V)]) -> bool {
+        self.iter().eq(other.iter().map(|(k, v)| (k, v)))
+    }
+}
+
+/// Compares the contents of both maps, regardless of order.
+impl<K, V, const C: usize, S, S2> PartialEq<HashMap<K, V, S2>> for SmallMap<K, V, C, S>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S2: BuildHasher,
+{
+    fn eq(&self, other: &HashMap<K, V, S2>) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+/// Compares the contents of both maps, regardless of order, like the
+/// `PartialEq` implementation of `IndexMap`.
+impl<K, V, const C: usize, S, S2> PartialEq<IndexMap<K, V, S2>> for SmallMap<K, V, C, S>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S2: BuildHasher,
+{
+    fn eq(&self, other: &IndexMap<K, V, S2>) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K, V, const C: usize, S> Default for MapData<K, V, C, S>
+where
+    S: Default,
+{
     fn default() -> Self {
-        MapData::Inline(SmallVec::new())
+        if C == 0 {
+            MapData::Heap(Box::default())
+        } else {
+            MapData::Inline(SmallVec::new())
+        }
     }
 }
 
@@ -501,7 +2098,7 @@ where
     type Output = V;
 
     fn index(&self, key: &Q) -> &Self::Output {
-        self.get(key).expect("SmallMap: index out of bounds")
+        self.get(key).expect("SmallMap: key not found")
     }
 }
 
@@ -512,7 +2109,7 @@ where
     S: BuildHasher,
 {
     fn index_mut(&mut self, key: &Q) -> &mut Self::Output {
-        self.get_mut(key).expect("SmallMap: index out of bounds")
+        self.get_mut(key).expect("SmallMap: key not found")
     }
 }
 
@@ -525,12 +2122,37 @@ pub enum Iter<'a, K, V> {
 impl<'a, K, V> Iterator for Iter<'a, K, V> {
     type Item = (&'a K, &'a V);
 
+    #[inline]
     fn next(&mut self) -> Option<Self::Item> {
         match self {
             Iter::Inline(iter) => iter.next().map(|i| (&i.0, &i.1)),
             Iter::Heap(iter) => iter.next(),
         }
     }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Iter::Inline(iter) => iter.size_hint(),
+            Iter::Heap(iter) => iter.size_hint(),
+        }
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        match self {
+            Iter::Inline(iter) => iter.nth(n).map(|i| (&i.0, &i.1)),
+            Iter::Heap(iter) => iter.nth(n),
+        }
+    }
+
+    #[inline]
+    fn last(self) -> Option<Self::Item> {
+        match self {
+            Iter::Inline(iter) => iter.last().map(|i| (&i.0, &i.1)),
+            Iter::Heap(iter) => iter.last(),
+        }
+    }
 }
 
 impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {
@@ -561,6 +2183,7 @@ pub enum IterMut<'a, K, V> {
 impl<'a, K, V> Iterator for IterMut<'a, K, V> {
     type Item = (&'a K, &'a mut V);
 
+    #[inline]
     fn next(&mut self) -> Option<Self::Item> {
         match self {
             IterMut::Inline(iter) => iter.next().map(|(k, v)| (&*k, v)),
@@ -578,6 +2201,17 @@ impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {
     }
 }
 
+impl<K, V> DoubleEndedIterator for IterMut<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            IterMut::Inline(iter) => iter.next_back().map(|(k, v)| (&*k, v)),
+            IterMut::Heap(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<K, V> FusedIterator for IterMut<'_, K, V> {}
+
 impl<K, V, const C: usize, S> IntoIterator for SmallMap<K, V, C, S> {
     type Item = (K, V);
 
@@ -586,7 +2220,7 @@ impl<K, V, const C: usize, S> IntoIterator for SmallMap<K, V, C, S> {
     fn into_iter(self) -> Self::IntoIter {
         match self.data {
             MapData::Inline(vec) => IntoIter::Inline(vec.into_iter()),
-            MapData::Heap(map) => IntoIter::Heap(map.into_iter()),
+            MapData::Heap(map) => IntoIter::Heap((*map).into_iter()),
         }
     }
 }
@@ -600,6 +2234,7 @@ pub enum Keys<'a, K, V> {
 impl<'a, K, V> Iterator for Keys<'a, K, V> {
     type Item = &'a K;
 
+    #[inline]
     fn next(&mut self) -> Option<Self::Item> {
         match self {
             Keys::Inline(iter) => iter.next().map(|(k, _)| k),
@@ -617,67 +2252,431 @@ impl<K, V> ExactSizeIterator for Keys<'_, K, V> {
     }
 }
 
-pub enum IntoIter<K, V, const C: usize> {
-    Inline(smallvec::IntoIter<[(K, V); C]>),
-    Heap(indexmap::map::IntoIter<K, V>),
+impl<K, V> DoubleEndedIterator for Keys<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            Keys::Inline(iter) => iter.next_back().map(|(k, _)| k),
+            Keys::Heap(iter) => iter.next_back(),
+        }
+    }
 }
 
-impl<K, V, const C: usize> Iterator for IntoIter<K, V, C> {
-    type Item = (K, V);
+impl<K, V> FusedIterator for Keys<'_, K, V> {}
+
+#[derive(Clone)]
+pub enum Values<'a, K, V> {
+    Inline(core::slice::Iter<'a, (K, V)>),
+    Heap(indexmap::map::Values<'a, K, V>),
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
 
+    #[inline]
     fn next(&mut self) -> Option<Self::Item> {
         match self {
-            IntoIter::Inline(iter) => iter.next().map(|i| (i.0, i.1)),
-            IntoIter::Heap(iter) => iter.next(),
+            Values::Inline(iter) => iter.next().map(|(_, v)| v),
+            Values::Heap(iter) => iter.next(),
         }
     }
 }
 
-impl<K, V, const C: usize> ExactSizeIterator for IntoIter<K, V, C> {
+impl<K, V> ExactSizeIterator for Values<'_, K, V> {
     fn len(&self) -> usize {
         match self {
-            IntoIter::Inline(iter) => iter.len(),
-            IntoIter::Heap(iter) => iter.len(),
+            Values::Inline(iter) => iter.len(),
+            Values::Heap(iter) => iter.len(),
         }
     }
 }
 
-impl<K, V, const C: usize> FusedIterator for IntoIter<K, V, C> {}
+impl<K, V> DoubleEndedIterator for Values<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            Values::Inline(iter) => iter.next_back().map(|(_, v)| v),
+            Values::Heap(iter) => iter.next_back(),
+        }
+    }
+}
 
-impl<K, V, const C: usize, S> FromIterator<(K, V)> for SmallMap<K, V, C, S>
-where
-    K: Hash + Eq,
+impl<K, V> FusedIterator for Values<'_, K, V> {}
+
+pub enum ValuesMut<'a, K, V> {
+    Inline(core::slice::IterMut<'a, (K, V)>),
+    Heap(indexmap::map::ValuesMut<'a, K, V>),
+}
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            ValuesMut::Inline(iter) => iter.next().map(|(_, v)| v),
+            ValuesMut::Heap(iter) => iter.next(),
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for ValuesMut<'_, K, V> {
+    fn len(&self) -> usize {
+        match self {
+            ValuesMut::Inline(iter) => iter.len(),
+            ValuesMut::Heap(iter) => iter.len(),
+        }
+    }
+}
+
+impl<K, V> DoubleEndedIterator for ValuesMut<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            ValuesMut::Inline(iter) => iter.next_back().map(|(_, v)| v),
+            ValuesMut::Heap(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<K, V> FusedIterator for ValuesMut<'_, K, V> {}
+
+pub enum IntoIter<K, V, const C: usize> {
+    Inline(smallvec::IntoIter<[(K, V); C]>),
+    Heap(indexmap::map::IntoIter<K, V>),
+}
+
+impl<K, V, const C: usize> Iterator for IntoIter<K, V, C> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            IntoIter::Inline(iter) => iter.next().map(|i| (i.0, i.1)),
+            IntoIter::Heap(iter) => iter.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            IntoIter::Inline(iter) => iter.size_hint(),
+            IntoIter::Heap(iter) => iter.size_hint(),
+        }
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        match self {
+            IntoIter::Inline(iter) => iter.nth(n),
+            IntoIter::Heap(iter) => iter.nth(n),
+        }
+    }
+
+    #[inline]
+    fn last(self) -> Option<Self::Item> {
+        match self {
+            IntoIter::Inline(iter) => iter.last(),
+            IntoIter::Heap(iter) => iter.last(),
+        }
+    }
+}
+
+impl<K, V, const C: usize> ExactSizeIterator for IntoIter<K, V, C> {
+    fn len(&self) -> usize {
+        match self {
+            IntoIter::Inline(iter) => iter.len(),
+            IntoIter::Heap(iter) => iter.len(),
+        }
+    }
+}
+
+impl<K, V, const C: usize> DoubleEndedIterator for IntoIter<K, V, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            IntoIter::Inline(iter) => iter.next_back(),
+            IntoIter::Heap(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<K, V, const C: usize> FusedIterator for IntoIter<K, V, C> {}
+
+/// An iterator over the key-values of a [`SmallMap`] together with the values
+/// for the same keys in another map, see [`SmallMap::iter_zip`].
+#[derive(Clone)]
+pub struct IterZip<'a, K, V, const C: usize, S> {
+    iter: Iter<'a, K, V>,
+    other: &'a SmallMap<K, V, C, S>,
+}
+
+impl<'a, K, V, const C: usize, S> Iterator for IterZip<'a, K, V, C, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    type Item = (&'a K, &'a V, Option<&'a V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(k, v)| (k, v, self.other.get(k)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, V, const C: usize, S> ExactSizeIterator for IterZip<'_, K, V, C, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+}
+
+impl<K, V, const C: usize, S> DoubleEndedIterator for IterZip<'_, K, V, C, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter
+            .next_back()
+            .map(|(k, v)| (k, v, self.other.get(k)))
+    }
+}
+
+impl<K, V, const C: usize, S> FusedIterator for IterZip<'_, K, V, C, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+}
+
+/// A draining iterator over a positional range of a [`SmallMap`], see
+/// [`SmallMap::drain_range`].
+pub enum Drain<'a, K, V, const C: usize> {
+    Inline(smallvec::Drain<'a, [(K, V); C]>),
+    Heap(indexmap::map::Drain<'a, K, V>),
+}
+
+impl<K, V, const C: usize> Iterator for Drain<'_, K, V, C> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Drain::Inline(iter) => iter.next(),
+            Drain::Heap(iter) => iter.next(),
+        }
+    }
+}
+
+impl<K, V, const C: usize> ExactSizeIterator for Drain<'_, K, V, C> {
+    fn len(&self) -> usize {
+        match self {
+            Drain::Inline(iter) => iter.len(),
+            Drain::Heap(iter) => iter.len(),
+        }
+    }
+}
+
+impl<K, V, const C: usize> DoubleEndedIterator for Drain<'_, K, V, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            Drain::Inline(iter) => iter.next_back(),
+            Drain::Heap(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<K, V, const C: usize> FusedIterator for Drain<'_, K, V, C> {}
+
+/// An iterator that removes and yields the key-values of a [`SmallMap`] that
+/// match a predicate, see [`SmallMap::extract_if`].
+pub struct ExtractIf<'a, K, V, const C: usize, S, F>
+where
+    F: FnMut(&K, &mut V) -> bool,
+{
+    map: &'a mut SmallMap<K, V, C, S>,
+    index: usize,
+    pred: F,
+}
+
+impl<K, V, const C: usize, S, F> Iterator for ExtractIf<'_, K, V, C, S, F>
+where
+    F: FnMut(&K, &mut V) -> bool,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.map.extract_next(&mut self.index, &mut self.pred)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.map.len() - self.index))
+    }
+}
+
+impl<K, V, const C: usize, S, F> FusedIterator for ExtractIf<'_, K, V, C, S, F> where
+    F: FnMut(&K, &mut V) -> bool
+{
+}
+
+impl<K, V, const C: usize, S, F> Drop for ExtractIf<'_, K, V, C, S, F>
+where
+    F: FnMut(&K, &mut V) -> bool,
+{
+    fn drop(&mut self) {
+        self.map.downgrade_if_small();
+    }
+}
+
+/// Converts a [`HashMap`] into a `SmallMap`, moving to the heap if it
+/// contains more than `C` key-values. The order of the resulting map is the
+/// arbitrary iteration order of the `HashMap`.
+impl<K, V, const C: usize, S, S2> From<HashMap<K, V, S2>> for SmallMap<K, V, C, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from(map: HashMap<K, V, S2>) -> Self {
+        Self::from_unique_iter(map.into_iter())
+    }
+}
+
+/// Converts a [`BTreeMap`] into a `SmallMap`, moving to the heap if it
+/// contains more than `C` key-values. The sorted order of the `BTreeMap`
+/// becomes the insertion order.
+impl<K, V, const C: usize, S> From<BTreeMap<K, V>> for SmallMap<K, V, C, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from(map: BTreeMap<K, V>) -> Self {
+        Self::from_unique_iter(map.into_iter())
+    }
+}
+
+impl<K, V, const C: usize, S> FromIterator<(K, V)> for SmallMap<K, V, C, S>
+where
+    K: Hash + Eq,
     S: BuildHasher + Default,
 {
     fn from_iter<I: IntoIterator<Item = (K, V)>>(iterable: I) -> Self {
         let iter = iterable.into_iter();
         let (lower_bound, _) = iter.size_hint();
-        if lower_bound <= C {
-            let mut map = Self {
-                data: MapData::Inline(Default::default()),
-            };
+        if Self::fits_inline(lower_bound) {
+            let mut map = Self::from_data(MapData::Inline(Default::default()));
             iter.for_each(|(key, value)| {
                 map.insert(key, value);
             });
             map
         } else {
             let mut index_map = IndexMap::from_iter(iter);
-            if index_map.len() <= C {
-                Self {
-                    data: MapData::Inline(index_map.drain(0..index_map.len()).collect()),
-                }
+            if Self::fits_inline(index_map.len()) {
+                Self::from_data(MapData::Inline(
+                    index_map.drain(0..index_map.len()).collect(),
+                ))
             } else {
-                Self {
-                    data: MapData::Heap(index_map),
-                }
+                Self::from_data(MapData::Heap(Box::new(index_map)))
+            }
+        }
+    }
+}
+
+/// The error returned when converting a collection with duplicate keys into
+/// a [`SmallMap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicateKeyError;
+
+impl fmt::Display for DuplicateKeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("SmallMap cannot be created from duplicate keys")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DuplicateKeyError {}
+
+impl<K, V, const C: usize, S> TryFrom<SmallVec<[(K, V); C]>> for SmallMap<K, V, C, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    type Error = DuplicateKeyError;
+
+    /// Convert the specified vec of key-value pairs into a `SmallMap`,
+    /// preserving their order.
+    ///
+    /// Returns [`DuplicateKeyError`] if any key occurs more than once. If the
+    /// vec is longer than the inline capacity, the data will be moved to the
+    /// heap.
+    fn try_from(vec: SmallVec<[(K, V); C]>) -> Result<Self, Self::Error> {
+        if Self::fits_inline(vec.len()) {
+            let has_duplicates = vec
+                .iter()
+                .enumerate()
+                .any(|(i, (key, _v))| vec[..i].iter().any(|(k, _v)| k == key));
+            if has_duplicates {
+                return Err(DuplicateKeyError);
+            }
+            Ok(Self::from_data(MapData::Inline(vec)))
+        } else {
+            let len = vec.len();
+            let map = vec.into_iter().collect::<IndexMap<_, _, _>>();
+            if map.len() == len {
+                Ok(Self::from_data(MapData::Heap(Box::new(map))))
+            } else {
+                Err(DuplicateKeyError)
+            }
+        }
+    }
+}
+
+impl<K, V, const C: usize, const N: usize, S> TryFrom<SmallMap<K, V, C, S>> for [(K, V); N] {
+    type Error = SmallMap<K, V, C, S>;
+
+    /// Convert the specified map into an array of its key-values, in insertion
+    /// order.
+    ///
+    /// Returns the map unchanged if it does not hold exactly `N` key-values.
+    fn try_from(map: SmallMap<K, V, C, S>) -> Result<Self, Self::Error> {
+        if map.len() != N {
+            return Err(map);
+        }
+        let vec: SmallVec<[(K, V); N]> = map.into_iter().collect();
+        Ok(vec
+            .into_inner()
+            .unwrap_or_else(|_| unreachable!("exactly N key-values fit inline")))
+    }
+}
+
+/// The error returned by [`SmallMap::from_columns`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FromColumnsError {
+    /// The number of keys and values differ.
+    LengthMismatch,
+    /// A key occurs more than once.
+    DuplicateKey,
+}
+
+impl fmt::Display for FromColumnsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FromColumnsError::LengthMismatch => {
+                f.write_str("SmallMap cannot be created from columns of different lengths")
             }
+            FromColumnsError::DuplicateKey => fmt::Display::fmt(&DuplicateKeyError, f),
         }
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for FromColumnsError {}
+
+/// Entry for an existing key-value pair or a vacant location to insert one.
 pub enum Entry<'a, K, V, const C: usize, S> {
-    Occupied(&'a mut SmallMap<K, V, C, S>, usize),
-    Vacant(&'a mut SmallMap<K, V, C, S>, K),
+    /// Existing slot with equivalent key.
+    Occupied(OccupiedEntry<'a, K, V, C, S>),
+    /// Vacant slot (no equivalent key in the map).
+    Vacant(VacantEntry<'a, K, V, C, S>),
 }
 
 impl<'a, K, V, const C: usize, S> Entry<'a, K, V, C, S>
@@ -691,13 +2690,66 @@ where
         F: FnOnce(&mut V),
     {
         match self {
-            Entry::Occupied(map, index) => {
-                f(map.get_index_mut(index).map(|(_k, v)| v).unwrap());
-                Entry::Occupied(map, index)
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            x => x,
+        }
+    }
+
+    /// Removes the entry if it is occupied and `f` returns `true` for its
+    /// value, leaving a vacant entry for the same key. Otherwise this is a
+    /// no-op.
+    ///
+    /// Like [`OccupiedEntry::remove`], the last key-value takes the place of
+    /// the removed one.
+    ///
+    /// ```
+    /// use more_collections::SmallMap;
+    ///
+    /// let mut refs: SmallMap<_, usize, 2> = SmallMap::new();
+    /// refs.insert("a", 1);
+    /// refs.entry("a").and_modify(|n| *n -= 1).and_remove_if(|n| *n == 0);
+    /// assert!(refs.is_empty());
+    /// ```
+    pub fn and_remove_if<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&V) -> bool,
+    {
+        match self {
+            Entry::Occupied(entry) => {
+                if !f(entry.get()) {
+                    return Entry::Occupied(entry);
+                }
+                let OccupiedEntry { map, index } = entry;
+                let (key, _v) = OccupiedEntry {
+                    map: &mut *map,
+                    index,
+                }
+                .remove_entry();
+                Entry::Vacant(VacantEntry { map, key })
             }
             x => x,
         }
     }
+
+    /// Gets a reference to the entry's key, either within the map if occupied,
+    /// or else the new key that was used to find the entry.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Return the index where the key-value pair exists or will be inserted.
+    pub fn index(&self) -> usize {
+        match self {
+            Entry::Occupied(entry) => entry.index(),
+            Entry::Vacant(entry) => entry.index(),
+        }
+    }
 }
 
 impl<'a, K, V, const C: usize, S> Entry<'a, K, V, C, S>
@@ -710,11 +2762,24 @@ where
     /// already existent value is returned.
     pub fn or_insert(self, default: V) -> &'a mut V {
         match self {
-            Entry::Vacant(map, key) => {
-                let (index, _) = map.insert_full(key, default);
-                &mut map[index]
-            }
-            Entry::Occupied(map, index) => &mut map[index],
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Inserts the result of `f` in the entry if it is vacant and returns a
+    /// mutable reference to it. Otherwise a mutable reference to an already
+    /// existent value is returned.
+    ///
+    /// `f` receives the key and the index at which the new entry will be
+    /// stored. It is not called if the entry is occupied.
+    pub fn or_insert_with_key_and_index<F>(self, f: F) -> &'a mut V
+    where
+        F: FnOnce(&K, usize) -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert_with(f),
         }
     }
 }
@@ -740,39 +2805,518 @@ where
     /// ```
     pub fn or_default(self) -> &'a mut V {
         match self {
-            Entry::Vacant(map, key) => {
-                let (index, _) = map.insert_full(key, Default::default());
-                &mut map[index]
-            }
-            Entry::Occupied(map, index) => &mut map[index],
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(V::default()),
         }
     }
 }
 
-impl<K, V, const C: usize, S> Debug for SmallMap<K, V, C, S>
+/// A view into an occupied entry in a [`SmallMap`]. It is part of the
+/// [`Entry`] enum.
+pub struct OccupiedEntry<'a, K, V, const C: usize, S> {
+    map: &'a mut SmallMap<K, V, C, S>,
+    index: usize,
+}
+
+impl<'a, K, V, const C: usize, S> OccupiedEntry<'a, K, V, C, S>
 where
-    K: Debug,
-    V: Debug,
+    K: Hash + Eq,
+    S: BuildHasher,
 {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.debug_map().entries(self.iter()).finish()
+    /// Gets a reference to the entry's key in the map.
+    pub fn key(&self) -> &K {
+        self.map.get_index(self.index).unwrap().0
     }
-}
 
-#[macro_export]
-macro_rules! smallmap {
-    // count helper: transform any expression into 1
-    (@one $x:expr) => (1usize);
-    ($($key:expr => $value:expr),*$(,)*) => ({
-        let count = 0usize $(+ $crate::smallmap!(@one $key))*;
-        #[allow(unused_mut)]
-        let mut map = $crate::SmallMap::new();
+    /// Gets a reference to the entry's value in the map.
+    pub fn get(&self) -> &V {
+        self.map.get_index(self.index).unwrap().1
+    }
+
+    /// Gets a mutable reference to the entry's value in the map.
+    ///
+    /// If you need a reference which may outlive the destruction of the entry,
+    /// see [`OccupiedEntry::into_mut`].
+    pub fn get_mut(&mut self) -> &mut V {
+        self.map.get_index_mut(self.index).unwrap().1
+    }
+
+    /// Converts into a mutable reference to the entry's value in the map, with
+    /// a lifetime bound to the map itself.
+    pub fn into_mut(self) -> &'a mut V {
+        self.map.get_index_mut(self.index).unwrap().1
+    }
+
+    /// Return the index of the key-value pair.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Sets the value of the entry to `value`, and returns the entry's old
+    /// value.
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the key-value from the map and returns its value.
+    ///
+    /// Like [`SmallMap::remove`], the last key-value takes its place, so order
+    /// is not preserved. Use [`OccupiedEntry::shift_remove`] to preserve
+    /// order.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Removes the key-value from the map and returns it.
+    ///
+    /// Like [`SmallMap::remove`], the last key-value takes its place, so order
+    /// is not preserved.
+    pub fn remove_entry(self) -> (K, V) {
+        match &mut self.map.data {
+            MapData::Inline(vec) => vec.swap_remove(self.index),
+            MapData::Heap(map) => {
+                let entry = map.swap_remove_index(self.index).unwrap();
+                self.map.downgrade_if_small();
+                entry
+            }
+        }
+    }
+
+    /// Removes the key-value from the map and returns its value, shifting all
+    /// following key-values to preserve order.
+    ///
+    /// Computational complexity: O(n)
+    pub fn shift_remove(self) -> V {
+        self.shift_remove_entry().1
+    }
+
+    /// Removes the key-value from the map and returns it, shifting all
+    /// following key-values to preserve order.
+    ///
+    /// Computational complexity: O(n)
+    pub fn shift_remove_entry(self) -> (K, V) {
+        match &mut self.map.data {
+            MapData::Inline(vec) => vec.remove(self.index),
+            MapData::Heap(map) => {
+                let entry = map.shift_remove_index(self.index).unwrap();
+                self.map.downgrade_if_small();
+                entry
+            }
+        }
+    }
+}
+
+/// A view into a vacant entry in a [`SmallMap`]. It is part of the [`Entry`]
+/// enum.
+pub struct VacantEntry<'a, K, V, const C: usize, S> {
+    map: &'a mut SmallMap<K, V, C, S>,
+    key: K,
+}
+
+impl<'a, K, V, const C: usize, S> VacantEntry<'a, K, V, C, S> {
+    /// Gets a reference to the key that was used to find the entry.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Takes ownership of the key, leaving the entry vacant.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Return the index where the key-value pair will be inserted.
+    pub fn index(&self) -> usize {
+        self.map.len()
+    }
+}
+
+impl<'a, K, V, const C: usize, S> VacantEntry<'a, K, V, C, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    /// Inserts the entry's key and the given value into the map, and returns a
+    /// mutable reference to the value.
+    pub fn insert(self, value: V) -> &'a mut V {
+        let (index, _) = self.map.insert_full(self.key, value);
+        &mut self.map[index]
+    }
+
+    /// Inserts the entry's key and the value computed by `f` into the map, and
+    /// returns a mutable reference to the value.
+    ///
+    /// `f` receives a reference to the key and the index at which the new
+    /// key-value pair will be stored.
+    pub fn insert_with<F>(self, f: F) -> &'a mut V
+    where
+        F: FnOnce(&K, usize) -> V,
+    {
+        let value = f(&self.key, self.index());
+        self.insert(value)
+    }
+}
+
+/// Entry for an existing key-value pair or a vacant location to insert one,
+/// found by a borrowed key. See [`SmallMap::entry_ref`].
+pub enum EntryRef<'a, 'b, K, Q: ?Sized, V, const C: usize, S> {
+    /// Existing slot with equivalent key.
+    Occupied(OccupiedEntry<'a, K, V, C, S>),
+    /// Vacant slot (no equivalent key in the map).
+    Vacant(VacantEntryRef<'a, 'b, K, Q, V, C, S>),
+}
+
+impl<'a, 'b, K, Q: ?Sized, V, const C: usize, S> EntryRef<'a, 'b, K, Q, V, C, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Modifies the entry if it is occupied. Otherwise this is a no-op.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        match self {
+            EntryRef::Occupied(mut entry) => {
+                f(entry.get_mut());
+                EntryRef::Occupied(entry)
+            }
+            x => x,
+        }
+    }
+
+    /// Return the index where the key-value pair exists or will be inserted.
+    pub fn index(&self) -> usize {
+        match self {
+            EntryRef::Occupied(entry) => entry.index(),
+            EntryRef::Vacant(entry) => entry.index(),
+        }
+    }
+}
+
+impl<'a, 'b, K, Q: ?Sized, V, const C: usize, S> EntryRef<'a, 'b, K, Q, V, C, S>
+where
+    K: Hash + Eq,
+    Q: ToOwned<Owned = K>,
+    S: BuildHasher + Default,
+{
+    /// Inserts the given default value in the entry if it is vacant and returns
+    /// a mutable reference to it. Otherwise a mutable reference to an
+    /// already existent value is returned.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Inserts the result of `f` in the entry if it is vacant and returns a
+    /// mutable reference to it. Otherwise a mutable reference to an already
+    /// existent value is returned.
+    pub fn or_insert_with<F>(self, f: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default value if empty,
+    /// and returns a mutable reference to the value in the entry.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(V::default()),
+        }
+    }
+}
+
+/// A view into a vacant entry in a [`SmallMap`], found by a borrowed key. It
+/// is part of the [`EntryRef`] enum.
+pub struct VacantEntryRef<'a, 'b, K, Q: ?Sized, V, const C: usize, S> {
+    map: &'a mut SmallMap<K, V, C, S>,
+    key: &'b Q,
+}
+
+impl<'a, 'b, K, Q: ?Sized, V, const C: usize, S> VacantEntryRef<'a, 'b, K, Q, V, C, S> {
+    /// Gets a reference to the borrowed key that was used to find the entry.
+    pub fn key(&self) -> &'b Q {
+        self.key
+    }
+
+    /// Return the index where the key-value pair will be inserted.
+    pub fn index(&self) -> usize {
+        self.map.len()
+    }
+}
+
+impl<'a, 'b, K, Q: ?Sized, V, const C: usize, S> VacantEntryRef<'a, 'b, K, Q, V, C, S>
+where
+    K: Hash + Eq,
+    Q: ToOwned<Owned = K>,
+    S: BuildHasher + Default,
+{
+    /// Inserts an owned copy of the entry's key and the given value into the
+    /// map, and returns a mutable reference to the value.
+    pub fn insert(self, value: V) -> &'a mut V {
+        let (index, _) = self.map.insert_full(self.key.to_owned(), value);
+        &mut self.map[index]
+    }
+}
+
+/// A builder for a raw entry of a [`SmallMap`], see
+/// [`SmallMap::raw_entry_mut`].
+///
+/// # Invariants
+///
+/// The raw entry API lets the caller edit keys in place and insert keys
+/// without checking for duplicates. The caller must ensure that:
+///
+/// - a key inserted through [`RawVacantEntryMut::insert`] is not already in
+///   the map, and
+/// - a key edited through [`RawOccupiedEntryMut::key_mut`] or
+///   [`RawOccupiedEntryMut::insert_key`] stays equal to the original key and
+///   keeps its hash.
+///
+/// Breaking these invariants is not undefined behavior, but lookups may then
+/// miss key-values and the map may hold duplicate keys, and this can change
+/// when the data moves between inline and heap storage.
+pub struct RawEntryBuilderMut<'a, K, V, const C: usize, S> {
+    map: &'a mut SmallMap<K, V, C, S>,
+}
+
+impl<'a, K, V, const C: usize, S> RawEntryBuilderMut<'a, K, V, C, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Finds the raw entry for the key equivalent to `key`.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn from_key<Q: ?Sized>(self, key: &Q) -> RawEntryMut<'a, K, V, C, S>
+    where
+        Q: Hash + Equivalent<K>,
+    {
+        let index = self.map.get_index_of(key);
+        self.into_entry(index)
+    }
+
+    /// Finds the raw entry for the first key, in insertion order, for which
+    /// `is_match` returns `true`.
+    ///
+    /// `IndexMap` does not support hash-based raw lookups, so this scans the
+    /// keys in both storage modes.
+    ///
+    /// Computational complexity: O(n)
+    pub fn search<F>(self, is_match: F) -> RawEntryMut<'a, K, V, C, S>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let index = self.map.keys().position(is_match);
+        self.into_entry(index)
+    }
+
+    fn into_entry(self, index: Option<usize>) -> RawEntryMut<'a, K, V, C, S> {
+        match index {
+            Some(index) => RawEntryMut::Occupied(RawOccupiedEntryMut {
+                map: self.map,
+                index,
+            }),
+            None => RawEntryMut::Vacant(RawVacantEntryMut { map: self.map }),
+        }
+    }
+}
+
+/// A raw entry for an existing key-value pair or a vacant location to insert
+/// one, see [`SmallMap::raw_entry_mut`].
+pub enum RawEntryMut<'a, K, V, const C: usize, S> {
+    /// Existing slot with a matching key.
+    Occupied(RawOccupiedEntryMut<'a, K, V, C, S>),
+    /// Vacant slot (no matching key in the map).
+    Vacant(RawVacantEntryMut<'a, K, V, C, S>),
+}
+
+impl<'a, K, V, const C: usize, S> RawEntryMut<'a, K, V, C, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    /// Ensures a key-value is in the entry by inserting the given key and
+    /// value if empty, and returns mutable references to the key and value in
+    /// the entry.
+    pub fn or_insert(self, key: K, value: V) -> (&'a mut K, &'a mut V) {
+        match self {
+            RawEntryMut::Occupied(entry) => entry.into_key_value_mut(),
+            RawEntryMut::Vacant(entry) => entry.insert(key, value),
+        }
+    }
+
+    /// Modifies the entry if it is occupied. Otherwise this is a no-op.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut K, &mut V),
+    {
+        match self {
+            RawEntryMut::Occupied(mut entry) => {
+                let (k, v) = entry.get_key_value_mut();
+                f(k, v);
+                RawEntryMut::Occupied(entry)
+            }
+            RawEntryMut::Vacant(entry) => RawEntryMut::Vacant(entry),
+        }
+    }
+}
+
+/// A raw view into an occupied entry in a [`SmallMap`]. It is part of the
+/// [`RawEntryMut`] enum.
+pub struct RawOccupiedEntryMut<'a, K, V, const C: usize, S> {
+    map: &'a mut SmallMap<K, V, C, S>,
+    index: usize,
+}
+
+impl<'a, K, V, const C: usize, S> RawOccupiedEntryMut<'a, K, V, C, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Return the index of the key-value pair.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Gets a reference to the entry's key in the map.
+    pub fn key(&self) -> &K {
+        self.map.get_index(self.index).unwrap().0
+    }
+
+    /// Gets a mutable reference to the entry's key in the map.
+    ///
+    /// The key must stay equal to the original key and keep its hash, see
+    /// [`RawEntryBuilderMut`].
+    pub fn key_mut(&mut self) -> &mut K {
+        self.get_key_value_mut().0
+    }
+
+    /// Gets a reference to the entry's value in the map.
+    pub fn get(&self) -> &V {
+        self.map.get_index(self.index).unwrap().1
+    }
+
+    /// Gets a mutable reference to the entry's value in the map.
+    pub fn get_mut(&mut self) -> &mut V {
+        self.map.get_index_mut(self.index).unwrap().1
+    }
+
+    /// Converts into a mutable reference to the entry's value in the map, with
+    /// a lifetime bound to the map itself.
+    pub fn into_mut(self) -> &'a mut V {
+        self.map.get_index_mut(self.index).unwrap().1
+    }
+
+    /// Gets mutable references to the entry's key and value in the map.
+    pub fn get_key_value_mut(&mut self) -> (&mut K, &mut V) {
+        self.map.get_index_mut_with_key(self.index).unwrap()
+    }
+
+    /// Converts into mutable references to the entry's key and value in the
+    /// map, with a lifetime bound to the map itself.
+    pub fn into_key_value_mut(self) -> (&'a mut K, &'a mut V) {
+        self.map.get_index_mut_with_key(self.index).unwrap()
+    }
+
+    /// Sets the value of the entry to `value`, and returns the entry's old
+    /// value.
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Sets the key of the entry to `key`, and returns the entry's old key.
+    ///
+    /// The key must be equal to the original key and have the same hash, see
+    /// [`RawEntryBuilderMut`].
+    pub fn insert_key(&mut self, key: K) -> K {
+        mem::replace(self.key_mut(), key)
+    }
+
+    /// Removes the key-value from the map and returns it.
+    ///
+    /// Like [`SmallMap::remove`], the last key-value takes its place, so order
+    /// is not preserved.
+    pub fn remove_entry(self) -> (K, V) {
+        OccupiedEntry {
+            map: self.map,
+            index: self.index,
+        }
+        .remove_entry()
+    }
+}
+
+/// A raw view into a vacant entry in a [`SmallMap`]. It is part of the
+/// [`RawEntryMut`] enum.
+pub struct RawVacantEntryMut<'a, K, V, const C: usize, S> {
+    map: &'a mut SmallMap<K, V, C, S>,
+}
+
+impl<'a, K, V, const C: usize, S> RawVacantEntryMut<'a, K, V, C, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    /// Inserts the given key and value into the map, and returns mutable
+    /// references to them.
+    ///
+    /// The key must not already be in the map, see [`RawEntryBuilderMut`].
+    pub fn insert(self, key: K, value: V) -> (&'a mut K, &'a mut V) {
+        let (index, _) = self.map.insert_full(key, value);
+        self.map.get_index_mut_with_key(index).unwrap()
+    }
+}
+
+impl<K, V, const C: usize, S> Debug for SmallMap<K, V, C, S>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Creates a [`SmallMap`] from a list of key-values.
+///
+/// The inline capacity can be given explicitly with `cap = C;` before the
+/// key-values, which avoids annotating the type of the binding:
+///
+/// ```
+/// use more_collections::smallmap;
+///
+/// let map = smallmap!(cap = 8; 1 => "a", 2 => "b");
+/// assert_eq!(8, map.inline_capacity());
+/// ```
+#[macro_export]
+macro_rules! smallmap {
+    // count helper: transform any expression into 1
+    (@one $x:expr) => (1usize);
+    (cap = $cap:expr; $($key:expr => $value:expr),*$(,)*) => ({
+        let map: $crate::SmallMap<_, _, { $cap }> = $crate::smallmap!($($key => $value),*);
+        map
+    });
+    ($($key:expr => $value:expr),*$(,)*) => ({
+        let count = 0usize $(+ $crate::smallmap!(@one $key))*;
+        #[allow(unused_mut)]
+        let mut map = $crate::SmallMap::new();
         if count <= map.inline_capacity() {
             $(map.insert($key, $value);)*
             map
         } else {
             #[allow(unused_mut)]
-            let mut index_map = indexmap::IndexMap::with_capacity_and_hasher(count, RandomState::default());
+            let mut index_map = indexmap::IndexMap::with_capacity_and_hasher(count, Default::default());
             $(index_map.insert($key, $value);)*
             $crate::SmallMap::from_map(index_map)
         }
@@ -800,6 +3344,7 @@ macro_rules! smallmap_inline {
 #[cfg(test)]
 mod test {
     use indexmap::indexmap;
+    use smallvec::smallvec;
 
     use super::*;
 
@@ -828,39 +3373,1304 @@ mod test {
     }
 
     #[test]
-    fn smallmap_macro_removes_duplicates() {
-        let map: SmallMap<_, _, 10> = smallmap! { 0 => 1, 0 => 2};
-        assert_eq!(1, map.len());
+    fn zero_inline_capacity_is_always_on_the_heap() {
+        let mut map: SmallMap<usize, usize, 0> = SmallMap::new();
+        let mut index_map = IndexMap::new();
+        assert!(!map.is_inline());
+
+        for i in 0..4 {
+            assert_eq!(index_map.insert(i, i * 10), map.insert(i, i * 10));
+            assert!(!map.is_inline());
+        }
+        assert_eq!(index_map.insert(1, 7), map.insert(1, 7));
+        assert!(map.iter_eq(index_map.iter().map(|(k, v)| (*k, *v))));
+
+        for i in 0..4 {
+            assert_eq!(index_map.shift_remove(&i), map.remove(&i));
+            assert!(!map.is_inline());
+        }
+        assert!(map.is_empty());
+
+        map.insert(5, 50);
+        map.clear();
+        assert!(!map.is_inline());
+        assert!(!SmallMap::<usize, usize, 0>::default().is_inline());
+        assert!(!SmallMap::<usize, usize, 0>::from_iter([]).is_inline());
+        assert!(!SmallMap::<usize, usize, 0>::from_map(IndexMap::new()).is_inline());
+    }
+
+    #[test]
+    fn get_index_entry_edits_and_removes_by_index() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = smallmap! { 1 => 'a', 0 => 'b', 4 => 'c', 7 => 'd' };
+            assert_eq!(inline, map.is_inline());
+
+            for index in 0..map.len() {
+                let mut entry = map.get_index_entry(index).unwrap();
+                assert_eq!(index, entry.index());
+                if *entry.key() % 2 == 0 {
+                    *entry.get_mut() = entry.get().to_ascii_uppercase();
+                }
+            }
+            assert!(map.iter_eq([(1, 'a'), (0, 'B'), (4, 'C'), (7, 'd')]));
+
+            let entry = map.get_index_entry(1).unwrap();
+            assert_eq!(&0, entry.key());
+            assert_eq!('B', entry.shift_remove());
+            assert_eq!('a', map.get_index_entry(0).unwrap().remove());
+            assert!(map.iter_eq([(7, 'd'), (4, 'C')]));
+            assert!(map.get_index_entry(2).is_none());
+        }
+        test::<2>(false);
+        test::<4>(true);
     }
 
-    #[test]
-    #[should_panic(expected = "smallmap_inline! cannot be initialized with duplicate keys")]
-    fn smallmap_inline_macro_fails_on_duplicates() {
-        smallmap_inline! { 0 => 1, 0 => 2};
+    #[test]
+    fn first_and_last_entry_remove() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> =
+                smallmap! { 0 => 0, 1 => 10, 2 => 20, 3 => 30, 4 => 40 };
+            assert_eq!(inline, map.is_inline());
+
+            let entry = map.first_entry().unwrap();
+            assert_eq!((&0, &0), (entry.key(), entry.get()));
+            assert_eq!(0, entry.remove());
+            assert!(map.iter_eq([(4, 40), (1, 10), (2, 20), (3, 30)]));
+
+            assert_eq!((3, 30), map.last_entry().unwrap().remove_entry());
+            assert!(map.iter_eq([(4, 40), (1, 10), (2, 20)]));
+
+            assert_eq!(40, map.first_entry().unwrap().shift_remove());
+            assert!(map.iter_eq([(1, 10), (2, 20)]));
+
+            *map.last_entry().unwrap().get_mut() += 1;
+            assert_eq!((2, 21), map.last_entry().unwrap().shift_remove_entry());
+            assert_eq!(10, map.first_entry().unwrap().remove());
+            assert!(map.is_empty());
+            assert!(map.is_inline());
+            assert!(map.first_entry().is_none());
+            assert!(map.last_entry().is_none());
+        }
+        test::<2>(false);
+        test::<5>(true);
+    }
+
+    #[test]
+    fn smallmap_macro_removes_duplicates() {
+        let map: SmallMap<_, _, 10> = smallmap! { 0 => 1, 0 => 2};
+        assert_eq!(1, map.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "smallmap_inline! cannot be initialized with duplicate keys")]
+    fn smallmap_inline_macro_fails_on_duplicates() {
+        smallmap_inline! { 0 => 1, 0 => 2};
+    }
+
+    #[test]
+    fn iter_iterates_in_insertion_order() {
+        fn test<const C: usize>(inline: bool) {
+            let inline_map: SmallMap<_, _, C> = smallmap! {
+                1 => 7,
+                0 => 1,
+                4 => 9
+            };
+            assert_eq!(inline, inline_map.is_inline());
+            assert_eq!(
+                vec![(&1, &7), (&0, &1), (&4, &9)],
+                inline_map.iter().collect::<Vec<_>>(),
+                "iter() does not return values in the correct order"
+            );
+            assert_eq!(
+                vec![(1, 7), (0, 1), (4, 9)],
+                inline_map.into_iter().collect::<Vec<_>>(),
+                "into_iter() does not return values in the correct order"
+            );
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn iter_nth_skips_to_entry() {
+        fn test<const C: usize>(inline: bool) {
+            let map: SmallMap<_, _, C> = SmallMap::from_iter((0..6).map(|i| (i, i * 10)));
+            assert_eq!(inline, map.is_inline());
+            for k in 0..6 {
+                assert_eq!(Some((&k, &(k * 10))), map.iter().nth(k));
+            }
+            assert_eq!(None, map.iter().nth(6));
+
+            let mut iter = map.iter();
+            assert_eq!((6, Some(6)), iter.size_hint());
+            assert_eq!(Some((&2, &20)), iter.nth(2));
+            assert_eq!((3, Some(3)), iter.size_hint());
+            assert_eq!(Some((&5, &50)), iter.clone().last());
+            assert_eq!(Some((&3, &30)), iter.next());
+            assert_eq!(
+                vec![(&0, &0), (&2, &20), (&4, &40)],
+                map.iter().step_by(2).collect::<Vec<_>>()
+            );
+
+            let mut into_iter = map.into_iter();
+            assert_eq!(Some((4, 40)), into_iter.nth(4));
+            assert_eq!((1, Some(1)), into_iter.size_hint());
+            assert_eq!(Some((5, 50)), into_iter.last());
+        }
+        test::<4>(false);
+        test::<8>(true);
+    }
+
+    #[test]
+    fn iter_zip_diffs_inline_against_heap() {
+        let old: SmallMap<_, _, 4> = smallmap! { "a" => 1, "b" => 2, "c" => 3 };
+        let new: SmallMap<_, _, 2> = smallmap! { "d" => 4, "c" => 3, "b" => 5 };
+        assert!(old.is_inline());
+        assert!(!new.is_inline());
+
+        assert_eq!(
+            vec![(&"a", &1, None), (&"b", &2, Some(&5)), (&"c", &3, Some(&3))],
+            old.iter_zip(&new).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(&"d", &4, None), (&"c", &3, Some(&3)), (&"b", &5, Some(&2))],
+            new.iter_zip(&old).collect::<Vec<_>>()
+        );
+        assert_eq!(3, old.iter_zip(&new).len());
+        assert_eq!(Some((&"c", &3, Some(&3))), old.iter_zip(&new).next_back());
+    }
+
+    #[test]
+    fn iter_eq_compares_entries_in_order() {
+        fn test<const C: usize>(inline: bool) {
+            let map: SmallMap<_, _, C> = smallmap! {
+                1 => 7,
+                0 => 1,
+                4 => 9
+            };
+            assert_eq!(inline, map.is_inline());
+
+            assert!(map.iter_eq([(1, 7), (0, 1), (4, 9)]));
+            // different order
+            assert!(!map.iter_eq([(0, 1), (1, 7), (4, 9)]));
+            // different value
+            assert!(!map.iter_eq([(1, 7), (0, 2), (4, 9)]));
+            // too short and too long
+            assert!(!map.iter_eq([(1, 7), (0, 1)]));
+            assert!(!map.iter_eq([(1, 7), (0, 1), (4, 9), (5, 5)]));
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn custom_hasher_spills_to_heap() {
+        fn test<S: BuildHasher + Default>() {
+            let mut map = SmallMap::<usize, &str, 2, S>::default();
+            map.insert(0, "zero");
+            map.insert(1, "one");
+            assert!(map.is_inline());
+
+            map.insert(2, "two");
+            assert!(!map.is_inline());
+            assert!(map.iter_eq([(0, "zero"), (1, "one"), (2, "two")]));
+            assert_eq!(Some(&"one"), map.get(&1));
+        }
+        test::<std::collections::hash_map::RandomState>();
+        test::<core::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>>();
+    }
+
+    #[test]
+    fn iterators_iterate_in_reverse_insertion_order() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = smallmap! {
+                1 => 7,
+                0 => 1,
+                4 => 9
+            };
+            assert_eq!(inline, map.is_inline());
+            assert_eq!(
+                vec![(&4, &9), (&0, &1), (&1, &7)],
+                map.iter().rev().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                vec![(&4, &mut 9), (&0, &mut 1), (&1, &mut 7)],
+                map.iter_mut().rev().collect::<Vec<_>>()
+            );
+            assert_eq!(vec![&4, &0, &1], map.keys().rev().collect::<Vec<_>>());
+            assert_eq!(vec![&9, &1, &7], map.values().rev().collect::<Vec<_>>());
+            assert_eq!(
+                vec![&mut 9, &mut 1, &mut 7],
+                map.values_mut().rev().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                vec![(4, 9), (0, 1), (1, 7)],
+                map.into_iter().rev().collect::<Vec<_>>()
+            );
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn iterators_are_fused() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = smallmap! { 1 => 7, 0 => 1 };
+            assert_eq!(inline, map.is_inline());
+
+            fn exhaust<I: Iterator>(mut iter: I) {
+                while iter.next().is_some() {}
+                for _ in 0..3 {
+                    assert!(iter.next().is_none());
+                }
+            }
+            exhaust(map.iter());
+            exhaust(map.iter_mut());
+            exhaust(map.keys());
+            exhaust(map.values());
+            exhaust(map.values_mut());
+            exhaust(map.into_iter());
+        }
+        test::<1>(false);
+        test::<2>(true);
+    }
+
+    #[test]
+    fn cloned_iterators_continue_independently() {
+        fn test<const C: usize>(inline: bool) {
+            let map: SmallMap<_, _, C> = smallmap! { 1 => 7, 0 => 1, 4 => 9 };
+            assert_eq!(inline, map.is_inline());
+
+            let mut iter = map.iter();
+            assert_eq!(Some((&1, &7)), iter.next());
+            let mut lookahead = iter.clone();
+            assert_eq!(Some((&0, &1)), lookahead.next());
+            assert_eq!(Some((&4, &9)), lookahead.next());
+            assert_eq!(None, lookahead.next());
+            assert_eq!(vec![(&0, &1), (&4, &9)], iter.collect::<Vec<_>>());
+
+            let mut keys = map.keys();
+            keys.next();
+            assert_eq!(vec![&0, &4], keys.clone().collect::<Vec<_>>());
+            assert_eq!(vec![&0, &4], keys.collect::<Vec<_>>());
+
+            let mut values = map.values();
+            values.next();
+            assert_eq!(vec![&1, &9], values.clone().collect::<Vec<_>>());
+            assert_eq!(vec![&1, &9], values.collect::<Vec<_>>());
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn sort_reorders_entries() {
+        fn test<const C: usize>() {
+            let mut map: SmallMap<_, _, C> = SmallMap::from_iter([(3, 'b'), (1, 'c'), (2, 'a')]);
+            map.sort_keys();
+            assert!(map.iter_eq([(1, 'c'), (2, 'a'), (3, 'b')]));
+            assert_eq!(Some(0), map.get_index_of(&1));
+
+            map.sort_by(|_, v1, _, v2| v1.cmp(v2));
+            assert!(map.iter_eq([(2, 'a'), (3, 'b'), (1, 'c')]));
+            assert_eq!(Some((&2, &'a')), map.get_index(0));
+
+            map.sort_unstable_by(|k1, _, k2, _| k2.cmp(k1));
+            assert!(map.iter_eq([(3, 'b'), (2, 'a'), (1, 'c')]));
+            assert_eq!(Some(2), map.get_index_of(&1));
+        }
+        test::<1>();
+        test::<3>();
+        test::<8>();
+    }
+
+    #[test]
+    fn binary_search_keys_test() {
+        fn test<const C: usize>() {
+            let map: SmallMap<_, _, C> = SmallMap::from_iter([(1, 'a'), (3, 'b'), (5, 'c')]);
+            assert_eq!(Ok(0), map.binary_search_keys(&1));
+            assert_eq!(Ok(2), map.binary_search_keys(&5));
+            assert_eq!(Err(0), map.binary_search_keys(&0));
+            assert_eq!(Err(2), map.binary_search_keys(&4));
+            assert_eq!(Err(3), map.binary_search_keys(&6));
+        }
+        test::<1>();
+        test::<3>();
+        test::<8>();
+    }
+
+    #[test]
+    fn reverse_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = smallmap! { 1 => 'a', 0 => 'b', 4 => 'c' };
+            assert_eq!(inline, map.is_inline());
+            map.reverse();
+            assert!(map.iter_eq([(4, 'c'), (0, 'b'), (1, 'a')]));
+            assert_eq!(Some(0), map.get_index_of(&4));
+            assert_eq!(Some(2), map.get_index_of(&1));
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn pop_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = smallmap! { 1 => 'a', 0 => 'b', 4 => 'c' };
+            assert_eq!(inline, map.is_inline());
+            assert_eq!(Some((4, 'c')), map.pop());
+            assert_eq!(Some((0, 'b')), map.pop());
+            assert_eq!(Some((1, 'a')), map.pop());
+            assert_eq!(None, map.pop());
+            assert!(map.is_empty());
+            assert!(map.is_inline());
+        }
+        test::<1>(false);
+        test::<2>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    // The assertions are evaluated at compile time on purpose.
+    #[allow(clippy::assertions_on_constants)]
+    fn fits_inline_is_const() {
+        const _: () = assert!(SmallMap::<u8, u8, 4>::INLINE_CAPACITY == 4);
+        const _: () = assert!(SmallMap::<u8, u8, 4>::fits_inline(4));
+        const _: () = assert!(!SmallMap::<u8, u8, 4>::fits_inline(5));
+        const _: () = assert!(!SmallMap::<u8, u8, 0>::fits_inline(0));
+
+        fn test<const C: usize>(len: usize) {
+            let map: SmallMap<_, _, C> = (0..len).map(|i| (i, i)).collect();
+            assert_eq!(
+                SmallMap::<usize, usize, C>::fits_inline(len),
+                map.is_inline()
+            );
+            assert_eq!(
+                SmallMap::<usize, usize, C>::INLINE_CAPACITY,
+                map.inline_capacity()
+            );
+        }
+        test::<0>(0);
+        test::<4>(0);
+        test::<4>(4);
+        test::<4>(5);
+    }
+
+    #[test]
+    fn pop_index_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = SmallMap::from_iter((0..6).map(|i| (i, i * 10)));
+            assert_eq!(inline, map.is_inline());
+            assert_eq!(Some(((2, 20), 5)), map.pop_index(2));
+            assert_eq!(Some(((3, 30), 4)), map.pop_index(2));
+            assert!(map.iter_eq([(0, 0), (1, 10), (4, 40), (5, 50)]));
+            assert_eq!(None, map.pop_index(4));
+            assert_eq!(Some(((5, 50), 3)), map.pop_index(3));
+            assert_eq!(Some(((0, 0), 2)), map.pop_index(0));
+            assert!(map.iter_eq([(1, 10), (4, 40)]));
+            assert!(map.is_inline());
+        }
+        test::<4>(false);
+        test::<6>(true);
+    }
+
+    #[test]
+    fn first_and_last_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = smallmap! { 1 => 'a', 0 => 'b', 4 => 'c' };
+            assert_eq!(inline, map.is_inline());
+            assert_eq!(Some((&1, &'a')), map.first());
+            assert_eq!(Some((&4, &'c')), map.last());
+
+            *map.first_mut().unwrap().1 = 'x';
+            *map.last_mut().unwrap().1 = 'z';
+            assert!(map.iter_eq([(1, 'x'), (0, 'b'), (4, 'z')]));
+
+            let mut empty: SmallMap<i32, char, C> = SmallMap::new();
+            assert_eq!(None, empty.first());
+            assert_eq!(None, empty.last());
+            assert_eq!(None, empty.first_mut());
+            assert_eq!(None, empty.last_mut());
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn split_off_test() {
+        let mut map: SmallMap<_, _, 3> = SmallMap::from_iter((0..8).map(|i| (i, i * 10)));
+        assert!(!map.is_inline());
+
+        let tail = map.split_off(6);
+        assert!(map.iter_eq((0..6).map(|i| (i, i * 10))));
+        assert!(!map.is_inline());
+        assert!(tail.iter_eq([(6, 60), (7, 70)]));
+        assert!(tail.is_inline());
+
+        // Like other removals, `self` only moves inline at half capacity.
+        let tail = map.split_off(3);
+        assert!(map.iter_eq([(0, 0), (1, 10), (2, 20)]));
+        assert!(!map.is_inline());
+        assert!(tail.iter_eq((3..6).map(|i| (i, i * 10))));
+        assert!(tail.is_inline());
+
+        let tail = map.split_off(1);
+        assert!(map.iter_eq([(0, 0)]));
+        assert!(map.is_inline());
+        assert!(tail.iter_eq([(1, 10), (2, 20)]));
+        assert!(tail.is_inline());
+
+        let tail = map.split_off(1);
+        assert!(map.iter_eq([(0, 0)]));
+        assert!(tail.is_empty());
+        assert!(tail.is_inline());
+
+        let mut map: SmallMap<_, _, 3> = SmallMap::from_iter((0..8).map(|i| (i, i * 10)));
+        let tail = map.split_off(2);
+        assert!(!map.is_inline());
+        assert!(tail.iter_eq((2..8).map(|i| (i, i * 10))));
+        assert!(!tail.is_inline());
+    }
+
+    #[test]
+    #[should_panic]
+    fn split_off_panics_past_len() {
+        let mut map: SmallMap<_, _, 3> = smallmap! { 0 => 0 };
+        map.split_off(2);
+    }
+
+    #[test]
+    fn append_test() {
+        let mut map: SmallMap<_, _, 3> = smallmap! { 0 => 'a', 1 => 'b' };
+        let mut other: SmallMap<_, _, 3> = smallmap! { 2 => 'c', 0 => 'x', 3 => 'd' };
+        map.append(&mut other);
+        assert!(map.iter_eq([(0, 'x'), (1, 'b'), (2, 'c'), (3, 'd')]));
+        assert!(!map.is_inline());
+        assert!(other.is_empty());
+        assert!(other.is_inline());
+
+        let mut map: SmallMap<_, _, 3> = smallmap! { 0 => 'a' };
+        let mut other: SmallMap<_, _, 3> = smallmap! { 1 => 'b' };
+        map.append(&mut other);
+        assert!(map.iter_eq([(0, 'a'), (1, 'b')]));
+        assert!(map.is_inline());
+        assert!(other.is_empty());
+    }
+
+    #[test]
+    fn push_grouped_groups_across_spill() {
+        let mut groups: SmallMap<_, Vec<_>, 2> = SmallMap::new();
+        groups.push_grouped(1, 'a');
+        groups.push_grouped(2, 'b');
+        groups.push_grouped(1, 'c');
+        assert!(groups.is_inline());
+        groups.push_grouped(3, 'd');
+        assert!(!groups.is_inline());
+        groups.push_grouped(2, 'e');
+        groups.push_grouped(1, 'f');
+        assert!(groups.iter_eq([
+            (1, vec!['a', 'c', 'f']),
+            (2, vec!['b', 'e']),
+            (3, vec!['d'])
+        ]));
+    }
+
+    #[test]
+    fn merge_with_sums_counts() {
+        let mut counts: SmallMap<_, _, 3> = smallmap! { "a" => 1, "b" => 2 };
+        counts.merge_with(smallmap! { "b" => 3, "a" => 4 }, |count, other| {
+            *count += other;
+        });
+        assert!(counts.iter_eq([("a", 5), ("b", 5)]));
+        assert!(counts.is_inline());
+
+        counts.merge_with(
+            smallmap! { "c" => 1, "b" => 1, "d" => 2 },
+            |count, other| {
+                *count += other;
+            },
+        );
+        assert!(counts.iter_eq([("a", 5), ("b", 6), ("c", 1), ("d", 2)]));
+        assert!(!counts.is_inline());
+
+        counts.merge_with(SmallMap::new(), |_, _| unreachable!());
+        assert_eq!(4, counts.len());
+    }
+
+    #[test]
+    fn map_retain_merges_colliding_keys() {
+        fn test<const C: usize>(inline_before: bool, inline_after: bool) {
+            let mut map: SmallMap<_, _, C> =
+                smallmap! { 'a' => 1, 'B' => 2, 'c' => 3, 'A' => 4, 'b' => 5 };
+            assert_eq!(inline_before, map.is_inline());
+
+            map.map_retain(|k, v| (v != 3).then(|| (k.to_ascii_lowercase(), v)));
+            assert!(map.iter_eq([('a', 4), ('b', 5)]));
+            assert_eq!(inline_after, map.is_inline());
+        }
+        test::<1>(false, false);
+        test::<2>(false, true);
+        test::<5>(true, true);
+    }
+
+    #[test]
+    fn eq_slice_and_maps() {
+        let map: SmallMap<_, _, 2> = smallmap! { 1 => 'a', 0 => 'b', 4 => 'c' };
+        assert!(!map.is_inline());
+
+        let vec = map.clone().into_vec();
+        assert!(map == vec[..]);
+        assert!(map == [(1, 'a'), (0, 'b'), (4, 'c')][..]);
+        assert!(map != [(0, 'b'), (1, 'a'), (4, 'c')][..]);
+        assert!(map != vec[..2]);
+
+        let hash_map = HashMap::from([(4, 'c'), (1, 'a'), (0, 'b')]);
+        assert!(map == hash_map);
+        assert!(map != HashMap::from([(4, 'c'), (1, 'a'), (0, 'x')]));
+        assert!(map != HashMap::from([(4, 'c'), (1, 'a')]));
+
+        assert!(map == indexmap! { 0 => 'b', 4 => 'c', 1 => 'a' });
+        assert!(map != indexmap! { 0 => 'b', 4 => 'c', 2 => 'a' });
+    }
+
+    #[test]
+    fn apply_doubles_values() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = smallmap! { 1 => 7, 0 => 1, 4 => 9 };
+            assert_eq!(inline, map.is_inline());
+            let mut keys = vec![];
+            map.apply(|k, v| {
+                keys.push(*k);
+                *v *= 2;
+            });
+            assert_eq!(vec![1, 0, 4], keys);
+            assert!(map.iter_eq([(1, 14), (0, 2), (4, 18)]));
+        }
+        test::<2>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn find_key_and_contains_value() {
+        fn test<const C: usize>(inline: bool) {
+            let map: SmallMap<_, _, C> = smallmap! { 1 => "a", 0 => "b", 4 => "c", 5 => "b" };
+            assert_eq!(inline, map.is_inline());
+            assert_eq!(Some(&0), map.find_key(|v| *v == "b"));
+            assert_eq!(Some(&4), map.find_key(|v| v.starts_with('c')));
+            assert_eq!(None, map.find_key(|v| *v == "x"));
+            assert!(map.contains_value(&"a"));
+            assert!(!map.contains_value(&"x"));
+        }
+        test::<2>(false);
+        test::<4>(true);
+    }
+
+    #[test]
+    fn reverse_iterators() {
+        fn test<const C: usize>(inline: bool) {
+            let map: SmallMap<_, _, C> = smallmap! { 1 => 'a', 0 => 'b', 4 => 'c' };
+            assert_eq!(inline, map.is_inline());
+            assert_eq!(
+                vec![(&4, &'c'), (&0, &'b'), (&1, &'a')],
+                map.rev_iter().collect::<Vec<_>>()
+            );
+            assert_eq!(vec![&4, &0, &1], map.keys_rev().collect::<Vec<_>>());
+            assert_eq!(vec![&'c', &'b', &'a'], map.values_rev().collect::<Vec<_>>());
+        }
+        test::<2>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn swap_and_shift_remove_entry() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = smallmap! { "a".to_string() => 1, "b".to_string() => 2, "c".to_string() => 3, "d".to_string() => 4 };
+            assert_eq!(inline, map.is_inline());
+
+            assert_eq!(Some(("a".to_string(), 1)), map.swap_remove_entry("a"));
+            assert!(map
+                .iter()
+                .map(|(k, v)| (k.as_str(), *v))
+                .eq([("d", 4), ("b", 2), ("c", 3)]));
+            assert_eq!(None, map.swap_remove_entry("a"));
+
+            assert_eq!(Some(("d".to_string(), 4)), map.shift_remove_entry("d"));
+            assert!(map
+                .iter()
+                .map(|(k, v)| (k.as_str(), *v))
+                .eq([("b", 2), ("c", 3)]));
+            assert_eq!(None, map.shift_remove_entry("d"));
+        }
+        test::<2>(false);
+        test::<4>(true);
+    }
+
+    #[test]
+    fn missed_remove_keeps_reserved_heap_map() {
+        let mut map: SmallMap<u32, u32, 4> = SmallMap::new();
+        map.insert(1, 1);
+        map.reserve(100);
+        let capacity = map.capacity();
+        assert!(!map.is_inline());
+
+        assert_eq!(None, map.remove(&99));
+        assert_eq!(None, map.swap_remove_full(&99));
+        assert_eq!(None, map.shift_remove_entry(&99));
+        assert!(!map.is_inline());
+        assert_eq!(capacity, map.capacity());
+
+        assert_eq!(Some(1), map.remove(&1));
+        assert!(map.is_inline());
+    }
+
+    #[test]
+    fn swap_values_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = smallmap! { 1 => 'a', 0 => 'b', 4 => 'c' };
+            assert_eq!(inline, map.is_inline());
+
+            assert!(map.swap_values(&4, &1));
+            assert!(map.iter_eq([(1, 'c'), (0, 'b'), (4, 'a')]));
+            assert!(map.swap_values(&1, &0));
+            assert!(map.iter_eq([(1, 'b'), (0, 'c'), (4, 'a')]));
+            assert!(map.swap_values(&0, &0));
+            assert!(map.iter_eq([(1, 'b'), (0, 'c'), (4, 'a')]));
+
+            assert!(!map.swap_values(&0, &3));
+            assert!(!map.swap_values(&3, &0));
+            assert!(!map.swap_values(&3, &3));
+            assert!(map.iter_eq([(1, 'b'), (0, 'c'), (4, 'a')]));
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn raw_entry_mut_inserts_and_mutates() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<String, usize, C> = SmallMap::new();
+            for word in ["a", "bb", "a", "ccc", "bb", "a"] {
+                match map.raw_entry_mut().from_key(word) {
+                    RawEntryMut::Occupied(mut entry) => *entry.get_mut() += 1,
+                    RawEntryMut::Vacant(entry) => {
+                        entry.insert(word.to_string(), 1);
+                    }
+                }
+            }
+            assert_eq!(inline, map.is_inline());
+            assert!(map.iter_eq([
+                ("a".to_string(), 3),
+                ("bb".to_string(), 2),
+                ("ccc".to_string(), 1)
+            ]));
+
+            let (key, value) = map
+                .raw_entry_mut()
+                .search(|k| k.len() == 2)
+                .and_modify(|_, v| *v *= 10)
+                .or_insert("dd".to_string(), 0);
+            assert_eq!(("bb", 20), (key.as_str(), *value));
+
+            // Keys may be edited in place as long as they stay equal.
+            let RawEntryMut::Occupied(mut entry) = map.raw_entry_mut().from_key("ccc") else {
+                panic!("ccc is present");
+            };
+            assert_eq!(2, entry.index());
+            entry.key_mut().shrink_to_fit();
+            assert_eq!(1, entry.insert(4));
+            assert_eq!(Some(&4), map.get("ccc"));
+
+            let RawEntryMut::Occupied(entry) = map.raw_entry_mut().search(|k| k.starts_with('a'))
+            else {
+                panic!("a is present");
+            };
+            assert_eq!(("a".to_string(), 3), entry.remove_entry());
+            assert!(matches!(
+                map.raw_entry_mut().from_key("a"),
+                RawEntryMut::Vacant(_)
+            ));
+            assert_eq!(2, map.len());
+        }
+        test::<2>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn get_pair_mut_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = smallmap! { 1 => 'a', 0 => 'b', 4 => 'c' };
+            assert_eq!(inline, map.is_inline());
+
+            let (four, one) = map.get_pair_mut(&4, &1).unwrap();
+            assert_eq!((&mut 'c', &mut 'a'), (&mut *four, &mut *one));
+            mem::swap(four, one);
+            assert!(map.iter_eq([(1, 'c'), (0, 'b'), (4, 'a')]));
+
+            let (zero, four) = map.get_pair_mut(&0, &4).unwrap();
+            *zero = 'x';
+            *four = 'y';
+            assert!(map.iter_eq([(1, 'c'), (0, 'x'), (4, 'y')]));
+
+            assert_eq!(None, map.get_pair_mut(&0, &0));
+            assert_eq!(None, map.get_pair_mut(&0, &3));
+            assert_eq!(None, map.get_pair_mut(&3, &0));
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn drain_range_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = SmallMap::from_iter((0..6).map(|i| (i, i * 10)));
+            assert_eq!(inline, map.is_inline());
+
+            let drained = map.drain_range(2..4);
+            assert_eq!(2, drained.len());
+            assert_eq!(vec![(2, 20), (3, 30)], drained.collect::<Vec<_>>());
+            assert!(map.iter_eq([(0, 0), (1, 10), (4, 40), (5, 50)]));
+            assert_eq!(inline, map.is_inline());
+            assert_eq!(Some(2), map.get_index_of(&4));
+
+            drop(map.drain_range(..1));
+            assert!(map.iter_eq([(1, 10), (4, 40), (5, 50)]));
+            assert_eq!(
+                vec![(5, 50)],
+                map.drain_range(2..).rev().collect::<Vec<_>>()
+            );
+            assert_eq!(0, map.drain_range(1..1).count());
+            assert!(map.iter_eq([(1, 10), (4, 40)]));
+        }
+        test::<3>(false);
+        test::<6>(true);
+    }
+
+    #[test]
+    #[should_panic]
+    fn drain_range_panics_past_len() {
+        let mut map: SmallMap<_, _, 3> = smallmap! { 0 => 0 };
+        map.drain_range(0..2);
+    }
+
+    #[test]
+    fn get_disjoint_mut_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = smallmap! { 1 => 'a', 0 => 'b', 4 => 'c' };
+            assert_eq!(inline, map.is_inline());
+
+            let [four, one] = map.get_disjoint_mut([&4, &1]).unwrap();
+            mem::swap(four, one);
+            assert!(map.iter_eq([(1, 'c'), (0, 'b'), (4, 'a')]));
+
+            assert_eq!(None, map.get_disjoint_mut([&0, &0]));
+            assert_eq!(None, map.get_disjoint_mut([&0, &3]));
+            assert_eq!(Some([]), map.get_disjoint_mut::<i32, 0>([]));
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn smallmap_macro_with_capacity() {
+        let map = smallmap!(cap = 8; 1 => "a", 2 => "b");
+        assert_eq!(8, map.inline_capacity());
+        assert!(map.is_inline());
+        assert!(map.iter_eq([(1, "a"), (2, "b")]));
+
+        let map = smallmap!(cap = 1; 1 => "a", 2 => "b",);
+        assert_eq!(1, map.inline_capacity());
+        assert!(!map.is_inline());
+
+        let map: SmallMap<i32, i32, 2> = smallmap!(cap = 2;);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn extract_if_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = SmallMap::from_iter((0..6).map(|i| (i, i * 10)));
+            assert_eq!(inline, map.is_inline());
+
+            let extracted = map.extract_if(|_, v| *v % 20 == 0).collect::<Vec<_>>();
+            assert_eq!(vec![(0, 0), (2, 20), (4, 40)], extracted);
+            assert!(map.iter_eq([(1, 10), (3, 30), (5, 50)]));
+            assert_eq!(Some(1), map.get_index_of(&3));
+        }
+        test::<2>(false);
+        test::<6>(true);
+    }
+
+    #[test]
+    fn extract_if_keeps_unvisited_entries_and_moves_inline() {
+        let mut map: SmallMap<_, _, 4> = SmallMap::from_iter((0..6).map(|i| (i, i)));
+        assert!(!map.is_inline());
+
+        assert_eq!(Some((0, 0)), map.extract_if(|_, _| true).next());
+        assert!(map.iter_eq((1..6).map(|i| (i, i))));
+        assert!(!map.is_inline());
+
+        assert_eq!(3, map.extract_if(|k, _| *k > 2).count());
+        assert!(map.iter_eq([(1, 1), (2, 2)]));
+        assert!(map.is_inline());
+    }
+
+    #[test]
+    fn to_sorted_vec_leaves_map_unchanged() {
+        fn test<const C: usize>(inline: bool) {
+            let map: SmallMap<_, _, C> = smallmap! { 3 => 'c', 1 => 'a', 4 => 'd', 0 => 'z' };
+            assert_eq!(inline, map.is_inline());
+
+            let mut expected = map.clone().into_vec();
+            expected.sort();
+            assert_eq!(expected, map.to_sorted_vec());
+            assert_eq!(
+                vec![(0, 'z'), (1, 'a'), (3, 'c'), (4, 'd')],
+                map.to_sorted_vec()
+            );
+            assert!(map.iter_eq([(3, 'c'), (1, 'a'), (4, 'd'), (0, 'z')]));
+        }
+        test::<2>(false);
+        test::<4>(true);
+    }
+
+    #[test]
+    fn into_vec_and_into_index_map() {
+        fn test<const C: usize>(inline: bool) {
+            let map: SmallMap<_, _, C> = smallmap! { 1 => 'a', 0 => 'b', 4 => 'c' };
+            assert_eq!(inline, map.is_inline());
+            assert_eq!(vec![(1, 'a'), (0, 'b'), (4, 'c')], map.clone().into_vec());
+            assert!(map
+                .into_index_map()
+                .into_iter()
+                .eq([(1, 'a'), (0, 'b'), (4, 'c')]));
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn from_hash_map_and_btree_map() {
+        let hash_map = (0..100).map(|i| (i, i * 2)).collect::<HashMap<_, _>>();
+        let map: SmallMap<_, _, 8> = SmallMap::from(hash_map.clone());
+        assert!(!map.is_inline());
+        assert_eq!(100, map.len());
+        assert!(hash_map.iter().all(|(k, v)| map.get(k) == Some(v)));
+
+        let btree_map = BTreeMap::from([(3, 'c'), (1, 'a'), (2, 'b')]);
+        let map: SmallMap<_, _, 8> = SmallMap::from(btree_map);
+        assert!(map.is_inline());
+        assert!(map.iter_eq([(1, 'a'), (2, 'b'), (3, 'c')]));
+    }
+
+    #[test]
+    fn shift_insert_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = smallmap! { 1 => 'a', 0 => 'b', 4 => 'c' };
+            assert_eq!(None, map.shift_insert(0, 7, 'd'));
+            assert_eq!(inline, map.is_inline());
+            assert!(map.iter_eq([(7, 'd'), (1, 'a'), (0, 'b'), (4, 'c')]));
+
+            assert_eq!(None, map.shift_insert(2, 8, 'e'));
+            assert!(map.iter_eq([(7, 'd'), (1, 'a'), (8, 'e'), (0, 'b'), (4, 'c')]));
+            assert_eq!(Some((&8, &'e')), map.get_index(2));
+
+            assert_eq!(None, map.shift_insert(5, 9, 'f'));
+            assert_eq!(Some(5), map.get_index_of(&9));
+
+            assert_eq!(Some('d'), map.shift_insert(3, 7, 'x'));
+            assert!(map.iter_eq([(1, 'a'), (8, 'e'), (0, 'b'), (7, 'x'), (4, 'c'), (9, 'f')]));
+            assert_eq!(Some('f'), map.shift_insert(0, 9, 'y'));
+            assert!(map.iter_eq([(9, 'y'), (1, 'a'), (8, 'e'), (0, 'b'), (7, 'x'), (4, 'c')]));
+        }
+        test::<3>(false);
+        test::<6>(true);
+    }
+
+    #[test]
+    fn insert_sorted_keeps_map_sorted_across_spill() {
+        let mut map: SmallMap<_, _, 3> = SmallMap::new();
+        assert_eq!((0, None), map.insert_sorted(5, 'a'));
+        assert_eq!((0, None), map.insert_sorted(1, 'b'));
+        assert_eq!((1, None), map.insert_sorted(3, 'c'));
+        assert!(map.is_inline());
+        assert_eq!((1, Some('c')), map.insert_sorted(3, 'd'));
+        assert!(map.iter_eq([(1, 'b'), (3, 'd'), (5, 'a')]));
+
+        assert_eq!((2, None), map.insert_sorted(4, 'e'));
+        assert!(!map.is_inline());
+        assert_eq!((0, None), map.insert_sorted(0, 'f'));
+        assert_eq!((5, None), map.insert_sorted(9, 'g'));
+        assert_eq!((2, Some('d')), map.insert_sorted(3, 'h'));
+        assert!(map.iter_eq([(0, 'f'), (1, 'b'), (3, 'h'), (4, 'e'), (5, 'a'), (9, 'g')]));
+        assert_eq!(Ok(3), map.binary_search_keys(&4));
+        assert_eq!(Err(5), map.binary_search_keys(&6));
+    }
+
+    #[test]
+    fn insert_sorted_replaces_missed_key_in_unsorted_map() {
+        test::<3>(true);
+        test::<1>(false);
+
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = smallmap! { 5 => 'a', 1 => 'b', 3 => 'c' };
+            assert_eq!(Err(3), map.binary_search_keys(&5));
+            assert_eq!((0, Some('a')), map.insert_sorted(5, 'd'));
+            assert_eq!(inline, map.is_inline());
+            assert!(map.iter_eq([(5, 'd'), (1, 'b'), (3, 'c')]));
+        }
+    }
+
+    #[test]
+    fn checked_insert_overwrites_within_capacity() {
+        let mut map: SmallMap<_, _, 2> = SmallMap::new();
+        assert_eq!(Ok(None), map.checked_insert(1, 'a'));
+        assert_eq!(Ok(None), map.checked_insert(2, 'b'));
+        assert_eq!(Ok(Some('a')), map.checked_insert(1, 'c'));
+        assert!(map.is_inline());
+        assert!(map.iter_eq([(1, 'c'), (2, 'b')]));
+    }
+
+    #[test]
+    fn checked_insert_refuses_to_spill() {
+        let mut map: SmallMap<_, _, 2> = smallmap! { 1 => 'a', 2 => 'b' };
+        assert_eq!(Err((3, 'c')), map.checked_insert(3, 'c'));
+        assert!(map.is_inline());
+        assert!(map.iter_eq([(1, 'a'), (2, 'b')]));
+
+        map.reserve(4);
+        assert!(!map.is_inline());
+        let capacity = map.capacity();
+        for key in 3.. {
+            if map.checked_insert(key, 'x').is_err() {
+                break;
+            }
+        }
+        assert_eq!(capacity, map.len());
+        assert_eq!(capacity, map.capacity());
+        assert_eq!(Ok(Some('x')), map.checked_insert(3, 'c'));
+    }
+
+    #[test]
+    fn remove_all_moves_heap_map_inline() {
+        let mut map: SmallMap<_, _, 4> = SmallMap::from_iter((0..8).map(|i| (i, i * 10)));
+        assert!(!map.is_inline());
+
+        map.remove_all([7, 1, 9, 3]);
+        assert!(!map.is_inline());
+        assert!(map.iter_eq([(0, 0), (2, 20), (4, 40), (5, 50), (6, 60)]));
+
+        map.remove_all([5, 0, 6, 0]);
+        assert!(map.is_inline());
+        assert!(map.iter_eq([(2, 20), (4, 40)]));
+
+        map.remove_all([4, 8]);
+        assert!(map.iter_eq([(2, 20)]));
+        map.remove_all([]);
+        assert!(map.iter_eq([(2, 20)]));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "insert_bounded called on a map holding more than `max` key-values")]
+    fn insert_bounded_panics_above_max_in_debug() {
+        let mut map: SmallMap<_, _, 2> = SmallMap::from_iter((0..5).map(|i| (i, i)));
+        map.insert_bounded(3, 10, 10);
+    }
+
+    #[test]
+    fn insert_bounded_after_truncating_to_max() {
+        let mut map: SmallMap<_, _, 2> = SmallMap::from_iter((0..5).map(|i| (i, i)));
+        map.drain_range(..map.len() - 3);
+        assert_eq!(Some((2, 2)), map.insert_bounded(3, 10, 10));
+        assert!(map.iter_eq([(3, 3), (4, 4), (10, 10)]));
+    }
+
+    #[test]
+    fn insert_bounded_evicts_oldest() {
+        let mut map: SmallMap<_, _, 2> = SmallMap::new();
+        assert_eq!(None, map.insert_bounded(3, 1, 'a'));
+        assert_eq!(None, map.insert_bounded(3, 2, 'b'));
+        assert_eq!(None, map.insert_bounded(3, 3, 'c'));
+        assert!(!map.is_inline());
+
+        assert_eq!(Some((1, 'a')), map.insert_bounded(3, 4, 'd'));
+        assert!(map.iter_eq([(2, 'b'), (3, 'c'), (4, 'd')]));
+
+        assert_eq!(None, map.insert_bounded(3, 3, 'x'));
+        assert!(map.iter_eq([(2, 'b'), (3, 'x'), (4, 'd')]));
+
+        assert_eq!(Some((2, 'b')), map.insert_bounded(3, 5, 'e'));
+        assert!(map.iter_eq([(3, 'x'), (4, 'd'), (5, 'e')]));
+
+        let mut empty: SmallMap<_, _, 2> = SmallMap::new();
+        assert_eq!(Some((6, 'f')), empty.insert_bounded(0, 6, 'f'));
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn shift_insert_existing_key_panics_at_len() {
+        let mut map: SmallMap<_, _, 3> = smallmap! { 1 => 'a', 0 => 'b' };
+        map.shift_insert(2, 1, 'c');
+    }
+
+    #[test]
+    fn move_index_and_swap_indices() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = SmallMap::from_iter((0..5).map(|i| (i, i)));
+            assert_eq!(inline, map.is_inline());
+
+            map.move_index(0, 3);
+            assert!(map.keys().eq(&[1, 2, 3, 0, 4]));
+            assert_eq!(Some(3), map.get_index_of(&0));
+
+            map.move_index(4, 1);
+            assert!(map.keys().eq(&[1, 4, 2, 3, 0]));
+            assert_eq!(Some(1), map.get_index_of(&4));
+
+            map.move_index(2, 2);
+            assert!(map.keys().eq(&[1, 4, 2, 3, 0]));
+
+            map.swap_indices(0, 4);
+            assert!(map.keys().eq(&[0, 4, 2, 3, 1]));
+            assert_eq!(Some(0), map.get_index_of(&0));
+            assert_eq!(Some(4), map.get_index_of(&1));
+        }
+        test::<2>(false);
+        test::<5>(true);
+    }
+
+    #[test]
+    fn rotate_left_and_right() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = SmallMap::from_iter((0..5).map(|i| (i, i * 10)));
+            assert_eq!(inline, map.is_inline());
+
+            map.rotate_left(2);
+            assert!(map.keys().eq(&[2, 3, 4, 0, 1]));
+            assert_eq!(Some((&2, &20)), map.get_index(0));
+            assert_eq!(Some(3), map.get_index_of(&0));
+
+            map.rotate_right(1);
+            assert!(map.keys().eq(&[1, 2, 3, 4, 0]));
+            assert_eq!(Some((&0, &0)), map.get_index(4));
+            assert_eq!(Some(0), map.get_index_of(&1));
+
+            map.rotate_left(0);
+            map.rotate_right(5);
+            assert!(map.keys().eq(&[1, 2, 3, 4, 0]));
+            map.rotate_left(5);
+            assert!(map.keys().eq(&[1, 2, 3, 4, 0]));
+            assert_eq!(Some(&40), map.get(&4));
+            assert_eq!(inline, map.is_inline());
+        }
+        test::<2>(false);
+        test::<5>(true);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rotate_left_panics_out_of_bounds() {
+        let mut map: SmallMap<_, _, 3> = smallmap! { 1 => 'a', 0 => 'b' };
+        map.rotate_left(3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn move_index_panics_out_of_bounds() {
+        let mut map: SmallMap<_, _, 3> = smallmap! { 1 => 'a', 0 => 'b' };
+        map.move_index(0, 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn swap_indices_panics_out_of_bounds() {
+        let mut map: SmallMap<_, _, 1> = smallmap! { 1 => 'a', 0 => 'b' };
+        map.swap_indices(2, 0);
+    }
+
+    #[test]
+    fn clear_moves_data_inline() {
+        let mut map: SmallMap<_, _, 2> = smallmap! { 1 => 'a', 0 => 'b', 4 => 'c' };
+        assert!(!map.is_inline());
+        map.clear();
+        assert!(map.is_empty());
+        assert!(map.is_inline());
+        map.insert(7, 'd');
+        assert!(map.iter_eq([(7, 'd')]));
+        assert!(map.is_inline());
+    }
+
+    #[test]
+    fn reserve_and_reserve_exact() {
+        let mut map: SmallMap<_, _, 4> = smallmap! { 1 => 'a', 0 => 'b' };
+        map.reserve(2);
+        map.reserve_exact(2);
+        assert!(map.is_inline());
+        assert_eq!(4, map.capacity());
+
+        map.reserve_exact(5);
+        assert!(!map.is_inline());
+        assert!(map.capacity() >= 7);
+        assert!(map.iter_eq([(1, 'a'), (0, 'b')]));
+
+        map.reserve(20);
+        assert!(map.capacity() >= 22);
+        map.reserve_exact(40);
+        assert!(map.capacity() >= 42);
+        assert!(map.iter_eq([(1, 'a'), (0, 'b')]));
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn reserve_overflow_panics() {
+        let mut map: SmallMap<_, _, 4> = smallmap! { 1 => 'a', 0 => 'b' };
+        map.reserve(usize::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn reserve_exact_overflow_panics() {
+        let mut map: SmallMap<_, _, 4> = smallmap! { 1 => 'a', 0 => 'b' };
+        map.reserve_exact(usize::MAX);
+    }
+
+    #[test]
+    fn try_reserve_test() {
+        let mut map: SmallMap<_, _, 4> = smallmap! { 1 => 'a', 0 => 'b' };
+        assert_eq!(Ok(()), map.try_reserve(2));
+        assert!(map.is_inline());
+
+        assert_eq!(Ok(()), map.try_reserve(3));
+        assert!(!map.is_inline());
+        assert!(map.iter_eq([(1, 'a'), (0, 'b')]));
+
+        assert_eq!(Ok(()), map.try_reserve(100));
+        assert!(map.iter_eq([(1, 'a'), (0, 'b')]));
+    }
+
+    #[test]
+    fn try_reserve_leaves_map_unchanged_on_error() {
+        let mut map: SmallMap<_, _, 4> = smallmap! { 1 => 'a', 0 => 'b' };
+        assert!(map.try_reserve(usize::MAX).is_err());
+        assert!(map.is_inline());
+        assert!(map.iter_eq([(1, 'a'), (0, 'b')]));
+
+        let mut map: SmallMap<_, _, 1> = smallmap! { 1 => 'a', 0 => 'b' };
+        assert!(map.try_reserve(usize::MAX).is_err());
+        assert!(map.iter_eq([(1, 'a'), (0, 'b')]));
+    }
+
+    #[test]
+    fn get_or_insert_with_groups_values() {
+        let mut map: SmallMap<char, Vec<&str>, 2> = SmallMap::new();
+        let mut calls = 0;
+        for word in [
+            "apple",
+            "avocado",
+            "banana",
+            "cherry",
+            "blueberry",
+            "apricot",
+        ] {
+            map.get_or_insert_with(word.chars().next().unwrap(), || {
+                calls += 1;
+                Vec::new()
+            })
+            .push(word);
+        }
+        assert_eq!(3, calls);
+        assert!(!map.is_inline());
+        assert!(map.iter_eq([
+            ('a', vec!["apple", "avocado", "apricot"]),
+            ('b', vec!["banana", "blueberry"]),
+            ('c', vec!["cherry"]),
+        ]));
+    }
+
+    #[test]
+    fn get_or_try_insert_with_only_inserts_on_ok() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<&str, u32, C> = smallmap! { "one" => 1 };
+            let parse = |s: &str| s.parse::<u32>();
+
+            assert_eq!(Ok(&mut 1), map.get_or_try_insert_with("one", || parse("x")));
+            assert!(map
+                .get_or_try_insert_with("two", || parse("not a number"))
+                .is_err());
+            assert!(map.iter_eq([("one", 1)]));
+
+            *map.get_or_try_insert_with("two", || parse("2")).unwrap() += 10;
+            assert!(map.iter_eq([("one", 1), ("two", 12)]));
+            assert_eq!(inline, map.is_inline());
+        }
+        test::<1>(false);
+        test::<2>(true);
+    }
+
+    #[test]
+    fn truncate_test() {
+        let mut map: SmallMap<_, _, 4> = SmallMap::from_iter((0..8).map(|i| (i, i * 10)));
+        assert!(!map.is_inline());
+
+        map.truncate(10);
+        assert_eq!(8, map.len());
+
+        map.truncate(3);
+        assert!(map.iter_eq([(0, 0), (1, 10), (2, 20)]));
+        assert!(!map.is_inline());
+
+        map.truncate(2);
+        assert!(map.iter_eq([(0, 0), (1, 10)]));
+        assert!(map.is_inline());
+
+        map.truncate(0);
+        assert!(map.is_empty());
+        assert!(map.is_inline());
+    }
+
+    #[test]
+    fn as_slice_is_only_available_inline() {
+        let mut map: SmallMap<_, _, 2> = smallmap! { 1 => 7, 0 => 1 };
+        assert_eq!(Some(&[(1, 7), (0, 1)][..]), map.as_slice());
+
+        map.insert(4, 9);
+        assert!(!map.is_inline());
+        assert_eq!(None, map.as_slice());
+    }
+
+    #[test]
+    fn as_index_map_is_only_available_on_heap() {
+        let mut map: SmallMap<_, _, 2> = smallmap! { 1 => 7, 0 => 1 };
+        assert!(map.as_index_map().is_none());
+        assert!(map.as_index_map_mut().is_none());
+
+        map.insert(4, 9);
+        assert!(!map.is_inline());
+        assert_eq!(
+            vec![(&1, &7), (&0, &1), (&4, &9)],
+            map.as_index_map().unwrap().iter().collect::<Vec<_>>()
+        );
+
+        let index_map = map.as_index_map_mut().unwrap();
+        index_map.insert(2, 3);
+        *index_map.get_mut(&1).unwrap() = 8;
+        assert!(map.iter_eq([(1, 8), (0, 1), (4, 9), (2, 3)]));
+        assert_eq!(Some(&3), map.get(&2));
     }
 
     #[test]
-    fn iter_iterates_in_insertion_order() {
-        fn test<const C: usize>(inline: bool) {
-            let inline_map: SmallMap<_, _, C> = smallmap! {
-                1 => 7,
-                0 => 1,
-                4 => 9
-            };
-            assert_eq!(inline, inline_map.is_inline());
-            assert_eq!(
-                vec![(&1, &7), (&0, &1), (&4, &9)],
-                inline_map.iter().collect::<Vec<_>>(),
-                "iter() does not return values in the correct order"
-            );
-            assert_eq!(
-                vec![(1, 7), (0, 1), (4, 9)],
-                inline_map.into_iter().collect::<Vec<_>>(),
-                "into_iter() does not return values in the correct order"
-            );
-        }
-        test::<1>(false);
-        test::<3>(true);
+    fn get_range_is_only_available_inline() {
+        let mut map: SmallMap<_, _, 4> = smallmap! { 1 => 7, 0 => 1, 3 => 2 };
+        assert_eq!(Some(&[(0, 1), (3, 2)][..]), map.get_range(1..));
+        assert_eq!(Some(&[(1, 7)][..]), map.get_range(..1));
+        assert_eq!(Some(&[][..]), map.get_range(3..3));
+        assert_eq!(None, map.get_range(2..4));
+
+        map.insert(4, 9);
+        map.insert(5, 5);
+        assert!(!map.is_inline());
+        assert_eq!(None, map.get_range(0..1));
     }
 
     #[test]
@@ -928,11 +4738,11 @@ mod test {
                 expected_return: Some((1,5,"five")),
             },
             TestCase {
-                name: "remove key from the middle swaps last item into middle when on the heap and moves inline",
+                name: "remove key from the middle swaps last item into middle when on the heap and stays on the heap above half the inline capacity",
                 initial_values: values[0..5].to_vec(),
                 remove_key: 5,
                 expected_inline_before: false,
-                expected_inline_after: true,
+                expected_inline_after: false,
                 expected_values: vec![
                     (10, "ten"),
                     (17, "seven-teen"),
@@ -942,11 +4752,11 @@ mod test {
                 expected_return: Some((1,5,"five")),
             },
             TestCase {
-                name: "remove key from the end moves map inline",
+                name: "remove key from the end stays on the heap above half the inline capacity",
                 initial_values: values[0..5].to_vec(),
                 remove_key: 93,
                 expected_inline_before: false,
-                expected_inline_after: true,
+                expected_inline_after: false,
                 expected_values: vec![
                     (10, "ten"),
                     (5, "five"),
@@ -1050,6 +4860,126 @@ mod test {
         }
     }
 
+    #[test]
+    fn hysteresis_prevents_repeated_migration() {
+        let mut map: SmallMap<usize, usize, 4> = (0..5).map(|i| (i, i)).collect();
+        assert!(!map.is_inline());
+
+        // hovering around C keeps the data on the heap
+        for _ in 0..3 {
+            map.remove(&4);
+            assert!(!map.is_inline());
+            map.insert(4, 4);
+            assert!(!map.is_inline());
+        }
+
+        map.remove(&4);
+        map.remove(&3);
+        assert_eq!(3, map.len());
+        assert!(!map.is_inline());
+
+        // at C / 2 the data moves inline
+        map.remove(&2);
+        assert!(map.is_inline());
+        assert!(map.iter_eq([(0, 0), (1, 1)]));
+    }
+
+    #[test]
+    fn retain_count_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<usize, usize, C> = (0..8).map(|i| (i, i)).collect();
+            assert_eq!(inline, map.is_inline());
+            let len = map.len();
+            assert_eq!(4, map.retain_count(|k, _v| k % 2 == 0));
+            assert_eq!(len - 4, map.len());
+            assert_eq!(0, map.retain_count(|_, _| true));
+            assert_eq!(4, map.retain_count(|_, _| false));
+            assert!(map.is_empty());
+        }
+        test::<4>(false);
+        test::<8>(true);
+    }
+
+    #[test]
+    fn retain_test() {
+        let mut map: SmallMap<usize, usize, 4> = (0..3).map(|i| (i, i * 10)).collect();
+        map.retain(|k, v| {
+            *v += 1;
+            *k != 1
+        });
+        assert!(map.is_inline());
+        assert!(map.iter_eq([(0, 1), (2, 21)]));
+
+        let mut map: SmallMap<usize, usize, 4> = (0..8).map(|i| (i, i)).collect();
+        map.retain(|k, _v| k % 2 == 0);
+        assert!(!map.is_inline());
+        assert!(map.iter_eq([(0, 0), (2, 2), (4, 4), (6, 6)]));
+
+        map.retain(|k, _v| *k < 4);
+        assert!(map.is_inline());
+        assert!(map.iter_eq([(0, 0), (2, 2)]));
+    }
+
+    #[test]
+    fn shrink_to_fit_test() {
+        let mut map: SmallMap<usize, usize, 4> = (0..8).map(|i| (i, i)).collect();
+        map.retain(|k, _v| *k < 3);
+        assert!(!map.is_inline());
+        map.shrink_to_fit();
+        assert!(!map.is_inline());
+
+        map.remove(&2);
+        assert!(map.is_inline());
+        map.shrink_to_fit();
+        assert!(map.is_inline());
+        assert!(map.iter_eq([(0, 0), (1, 1)]));
+    }
+
+    #[test]
+    fn shrink_to_test() {
+        let mut map: SmallMap<usize, usize, 4> = (0..64).map(|i| (i, i)).collect();
+        map.retain(|k, _v| *k < 8);
+        assert!(map.capacity() >= 64);
+
+        map.shrink_to(16);
+        assert!(!map.is_inline());
+        assert!(map.capacity() >= 16);
+        assert!(map.capacity() < 64);
+
+        map.shrink_to(0);
+        assert!(!map.is_inline());
+        assert!(map.capacity() >= 8);
+
+        map.retain(|k, _v| *k < 3);
+        assert!(!map.is_inline());
+        map.shrink_to(8);
+        assert!(!map.is_inline());
+        map.shrink_to(4);
+        assert!(map.is_inline());
+        assert_eq!(4, map.capacity());
+        assert!(map.iter_eq([(0, 0), (1, 1), (2, 2)]));
+    }
+
+    #[test]
+    fn compact_moves_data_inline_when_it_fits() {
+        let mut map: SmallMap<usize, usize, 3> = (0..6).map(|i| (i, i)).collect();
+        map.retain(|k, _v| *k < 2);
+        assert!(!map.is_inline());
+        map.compact();
+        assert!(map.is_inline());
+        assert!(map.iter_eq([(0, 0), (1, 1)]));
+
+        map.compact();
+        assert!(map.is_inline());
+
+        let mut map: SmallMap<usize, usize, 3> = (0..64).map(|i| (i, i)).collect();
+        map.retain(|k, _v| *k < 8);
+        map.compact();
+        assert!(!map.is_inline());
+        assert_eq!(8, map.len());
+        assert!(map.capacity() < 64);
+    }
+
     #[test]
     fn insert_and_insert_full_tests() {
         // Test cases:
@@ -1292,6 +5222,69 @@ mod test {
         test::<3>(true);
     }
 
+    // Type whose equality ignores the payload
+    #[derive(Debug)]
+    struct PayloadKey(usize, &'static str);
+
+    impl Hash for PayloadKey {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            self.0.hash(state);
+        }
+    }
+
+    impl PartialEq for PayloadKey {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for PayloadKey {}
+
+    #[test]
+    fn get_full_and_get_key_value_return_stored_key() {
+        fn test<const C: usize>(inline: bool) {
+            let map: SmallMap<PayloadKey, usize, C> = smallmap! {
+                PayloadKey(2, "stored") => 222,
+                PayloadKey(1, "stored") => 111,
+                PayloadKey(3, "stored") => 333
+            };
+            assert_eq!(inline, map.is_inline());
+
+            let (index, key, value) = map.get_full(&PayloadKey(1, "lookup")).unwrap();
+            assert_eq!((1, 111), (index, *value));
+            assert_eq!("stored", key.1);
+
+            let (key, value) = map.get_key_value(&PayloadKey(3, "lookup")).unwrap();
+            assert_eq!(333, *value);
+            assert_eq!("stored", key.1);
+
+            assert!(map.get_full(&PayloadKey(7, "lookup")).is_none());
+            assert!(map.get_key_value(&PayloadKey(7, "lookup")).is_none());
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn replace_value_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<&'static str, usize, C> =
+                smallmap! {"2" => 222, "1" => 111, "3" => 333};
+            assert_eq!(inline, map.is_inline());
+
+            // present -> replaced, old value returned
+            assert_eq!(Ok(111), map.replace_value(&"1", 1));
+            assert_eq!(Some(&1), map.get(&"1"));
+
+            // absent -> not inserted, value returned
+            assert_eq!(Err(7), map.replace_value(&"7", 7));
+            assert_eq!(None, map.get(&"7"));
+            assert_eq!(3, map.len());
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
     #[test]
     fn get_index_test() {
         fn test<const C: usize>(inline: bool) {
@@ -1339,6 +5332,46 @@ mod test {
         let _ = map[5];
     }
 
+    #[test]
+    fn index_by_key_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<&'static str, usize, C> =
+                smallmap! {"2" => 222, "1" => 111, "3" => 333};
+            assert_eq!(inline, map.is_inline());
+
+            assert_eq!(222, map[&"2"]);
+            assert_eq!(111, map["1"]);
+            map[&"3"] = 3;
+            assert_eq!(3, map[&"3"]);
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    #[should_panic(expected = "SmallMap: key not found")]
+    fn index_by_key_panics_on_absent_key_inline() {
+        let map: SmallMap<&'static str, usize, 3> = smallmap! {"2" => 222, "1" => 111, "3" => 333};
+        assert!(map.is_inline());
+        let _ = map[&"5"];
+    }
+
+    #[test]
+    #[should_panic(expected = "SmallMap: key not found")]
+    fn index_by_key_panics_on_absent_key_heap() {
+        let map: SmallMap<&'static str, usize, 1> = smallmap! {"2" => 222, "1" => 111, "3" => 333};
+        assert!(!map.is_inline());
+        let _ = map[&"5"];
+    }
+
+    #[test]
+    #[should_panic(expected = "SmallMap: key not found")]
+    fn index_mut_by_key_panics_on_absent_key() {
+        let mut map: SmallMap<&'static str, usize, 1> =
+            smallmap! {"2" => 222, "1" => 111, "3" => 333};
+        map[&"5"] = 5;
+    }
+
     #[test]
     fn get_index_mut_test() {
         fn test<const C: usize>(inline: bool) {
@@ -1443,6 +5476,41 @@ mod test {
         test::<3>(true);
     }
 
+    #[test]
+    fn entry_and_remove_if_counts_down_across_spill() {
+        let mut refs: SmallMap<_, usize, 2> = SmallMap::new();
+        for key in ["a", "b", "a", "c", "b", "a"] {
+            *refs.entry(key).or_insert(0) += 1;
+        }
+        assert!(!refs.is_inline());
+        assert!(refs.iter_eq([("a", 3), ("b", 2), ("c", 1)]));
+
+        for key in ["c", "b", "a"] {
+            let entry = refs
+                .entry(key)
+                .and_modify(|n| *n -= 1)
+                .and_remove_if(|n| *n == 0);
+            assert_eq!(key == "c", matches!(entry, Entry::Vacant(_)));
+        }
+        assert!(refs.iter_eq([("a", 2), ("b", 1)]));
+        assert!(!refs.is_inline());
+
+        let entry = refs
+            .entry("b")
+            .and_modify(|n| *n -= 1)
+            .and_remove_if(|n| *n == 0);
+        assert_eq!("b", *entry.key());
+        assert_eq!(1, entry.index());
+        assert!(refs.iter_eq([("a", 2)]));
+        assert!(refs.is_inline());
+
+        refs.entry("a")
+            .and_modify(|n| *n -= 1)
+            .and_remove_if(|n| *n == 0);
+        refs.entry("d").and_remove_if(|_| unreachable!());
+        assert!(refs.iter_eq([("a", 1)]));
+    }
+
     #[test]
     fn entry_or_insert_test() {
         fn test<const C: usize>(inline: bool) {
@@ -1465,6 +5533,116 @@ mod test {
         test::<3>(true);
     }
 
+    #[test]
+    fn entry_vacant_insert_with_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<&'static str, String, C> =
+                smallmap! {"2" => "222".to_string(), "1" => "111".to_string()};
+
+            // vacant -> f receives key and future index
+            let value = match map.entry("3") {
+                Entry::Vacant(entry) => entry.insert_with(|k, i| format!("{k}@{i}")),
+                Entry::Occupied(_) => panic!("expected vacant entry"),
+            };
+            assert_eq!("3@2", value);
+            assert_eq!(inline, map.is_inline());
+            assert_eq!(Some((&"3", &"3@2".to_string())), map.get_index(2));
+
+            // occupied -> f is never called
+            let value = map
+                .entry("1")
+                .or_insert_with_key_and_index(|_, _| panic!("f called on occupied entry"));
+            assert_eq!("111", value);
+            assert_eq!(3, map.len());
+        }
+        test::<2>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn entry_ref_only_creates_owned_key_on_insert() {
+        use core::borrow::Borrow;
+        use core::sync::atomic::AtomicUsize;
+        use core::sync::atomic::Ordering;
+
+        static TO_OWNED_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Debug, Hash, PartialEq, Eq)]
+        struct KeyRef(u32);
+        #[derive(Debug, Hash, PartialEq, Eq)]
+        struct Key(KeyRef);
+
+        impl Borrow<KeyRef> for Key {
+            fn borrow(&self) -> &KeyRef {
+                &self.0
+            }
+        }
+        impl ToOwned for KeyRef {
+            type Owned = Key;
+
+            fn to_owned(&self) -> Key {
+                TO_OWNED_CALLS.fetch_add(1, Ordering::Relaxed);
+                Key(KeyRef(self.0))
+            }
+        }
+
+        fn test<const C: usize>(inline: bool) {
+            TO_OWNED_CALLS.store(0, Ordering::Relaxed);
+            let mut map: SmallMap<Key, u32, C> = SmallMap::new();
+            map.insert(Key(KeyRef(1)), 10);
+            map.insert(Key(KeyRef(2)), 20);
+
+            *map.entry_ref(&KeyRef(1)).or_insert(0) += 1;
+            map.entry_ref(&KeyRef(2))
+                .and_modify(|v| *v += 2)
+                .or_default();
+            assert_eq!(0, TO_OWNED_CALLS.load(Ordering::Relaxed));
+
+            match map.entry_ref(&KeyRef(3)) {
+                EntryRef::Vacant(entry) => {
+                    assert_eq!(&KeyRef(3), entry.key());
+                    assert_eq!(2, entry.index());
+                    assert_eq!(&mut 30, entry.insert(30));
+                }
+                EntryRef::Occupied(_) => panic!("expected vacant entry"),
+            }
+            assert_eq!(1, TO_OWNED_CALLS.load(Ordering::Relaxed));
+            assert_eq!(inline, map.is_inline());
+            assert!(map
+                .iter()
+                .map(|(k, v)| (k.0 .0, *v))
+                .eq([(1, 11), (2, 22), (3, 30)]));
+        }
+        test::<2>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn locate_or_reserve_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<String, usize, C> = SmallMap::default();
+            map.insert("a".to_string(), 1);
+            map.insert("b".to_string(), 2);
+
+            // existing key
+            assert_eq!((1, false), map.locate_or_reserve("b"));
+            assert_eq!(2, map.len());
+
+            // new key reserves slot with placeholder value
+            assert_eq!((2, true), map.locate_or_reserve("c"));
+            assert_eq!(inline, map.is_inline());
+            assert_eq!(Some((&"c".to_string(), &0)), map.get_index(2));
+            map[2] = 3;
+            assert_eq!(Some(&3), map.get("c"));
+
+            // reserved key is found afterwards
+            assert_eq!((2, false), map.locate_or_reserve("c"));
+            assert_eq!(3, map.len());
+        }
+        test::<2>(false);
+        test::<3>(true);
+    }
+
     #[test]
     fn exact_size_iterator_test() {
         fn test<const C: usize>(inline: bool) {
@@ -1555,6 +5733,14 @@ mod test {
         assert_eq!(data, output);
     }
 
+    #[test]
+    fn from_iterator_without_size_hint_overwrites_after_moving_to_heap() {
+        let data = [(0, 'a'), (1, 'b'), (2, 'c'), (3, 'd'), (1, 'x'), (4, 'e')];
+        let map = SmallMap::<_, _, 2>::from_iter(data.into_iter().filter(|_| true));
+        assert!(!map.is_inline());
+        assert!(map.iter_eq([(0, 'a'), (1, 'x'), (2, 'c'), (3, 'd'), (4, 'e')]));
+    }
+
     #[test]
     fn from_iterator_duplicate_keys() {
         // input fits inline, should stay inline
@@ -1574,6 +5760,154 @@ mod test {
         assert!(map.is_inline());
     }
 
+    #[test]
+    fn try_from_small_vec_test() {
+        let inline = SmallMap::<_, _, 3>::try_from(smallvec![(1, 7), (0, 1), (4, 9)]).unwrap();
+        assert!(inline.is_inline());
+        assert!(inline.iter_eq([(1, 7), (0, 1), (4, 9)]));
+
+        let heap = SmallMap::<_, _, 2>::try_from(smallvec![(1, 7), (0, 1), (4, 9)]).unwrap();
+        assert!(!heap.is_inline());
+        assert!(heap.iter_eq([(1, 7), (0, 1), (4, 9)]));
+
+        assert_eq!(
+            Err(DuplicateKeyError),
+            SmallMap::<_, _, 3>::try_from(smallvec![(1, 7), (0, 1), (1, 9)])
+        );
+        assert_eq!(
+            Err(DuplicateKeyError),
+            SmallMap::<_, _, 2>::try_from(smallvec![(1, 7), (0, 1), (1, 9)])
+        );
+    }
+
+    #[test]
+    fn try_into_array_test() {
+        fn test<const C: usize>(inline: bool) {
+            let map: SmallMap<_, _, C> = smallmap! { 1 => 'a', 0 => 'b', 4 => 'c' };
+            assert_eq!(inline, map.is_inline());
+            let array: [(i32, char); 3] = map.clone().try_into().unwrap();
+            assert_eq!([(1, 'a'), (0, 'b'), (4, 'c')], array);
+
+            let too_short: Result<[(i32, char); 2], _> = map.clone().try_into();
+            assert!(too_short
+                .unwrap_err()
+                .iter_eq([(1, 'a'), (0, 'b'), (4, 'c')]));
+            let too_long: Result<[(i32, char); 4], _> = map.try_into();
+            let map = too_long.unwrap_err();
+            assert_eq!(inline, map.is_inline());
+            assert!(map.iter_eq([(1, 'a'), (0, 'b'), (4, 'c')]));
+
+            let empty: [(i32, char); 0] = SmallMap::<_, _, C>::new().try_into().unwrap();
+            assert_eq!(0, empty.len());
+        }
+        test::<2>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn from_vec_dedup_keeps_first_occurrence() {
+        let map = SmallMap::<_, _, 3>::from_vec_dedup(smallvec![(1, 7), (0, 1), (1, 9), (0, 2)]);
+        assert!(map.is_inline());
+        assert!(map.iter_eq([(1, 7), (0, 1)]));
+
+        let map = SmallMap::<_, _, 2>::from_vec_dedup(smallvec![(1, 7), (0, 1), (1, 9), (4, 2)]);
+        assert!(!map.is_inline());
+        assert!(map.iter_eq([(1, 7), (0, 1), (4, 2)]));
+    }
+
+    #[test]
+    fn from_iter_first_and_last_wins() {
+        let rows = [("a", 1), ("b", 2), ("a", 3), ("c", 4), ("b", 5)];
+
+        let map = SmallMap::<_, _, 3>::from_iter_first_wins(rows);
+        assert!(map.is_inline());
+        assert!(map.iter_eq([("a", 1), ("b", 2), ("c", 4)]));
+
+        let map = SmallMap::<_, _, 3>::from_iter_last_wins(rows);
+        assert!(map.is_inline());
+        assert!(map.iter_eq([("a", 3), ("b", 5), ("c", 4)]));
+
+        let map = SmallMap::<_, _, 2>::from_iter_first_wins(rows);
+        assert!(!map.is_inline());
+        assert!(map.iter_eq([("a", 1), ("b", 2), ("c", 4)]));
+
+        let map = SmallMap::<_, _, 2>::from_iter_last_wins(rows);
+        assert!(!map.is_inline());
+        assert!(map.iter_eq([("a", 3), ("b", 5), ("c", 4)]));
+    }
+
+    #[test]
+    fn columns_round_trip() {
+        fn test<const C: usize>(inline: bool) {
+            let map: SmallMap<_, _, C> = smallmap! {
+                1 => "seven",
+                0 => "one",
+                4 => "nine"
+            };
+            assert_eq!(inline, map.is_inline());
+
+            let (keys, values) = map.clone().into_columns();
+            assert_eq!(&[1, 0, 4], keys.as_slice());
+            assert_eq!(&["seven", "one", "nine"], values.as_slice());
+
+            let round_trip = SmallMap::<_, _, C>::from_columns(keys, values).unwrap();
+            assert_eq!(inline, round_trip.is_inline());
+            assert_eq!(map, round_trip);
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn from_columns_rejects_invalid_columns() {
+        assert_eq!(
+            Err(FromColumnsError::LengthMismatch),
+            SmallMap::<_, _, 3>::from_columns(smallvec![1, 0], smallvec!["seven"])
+        );
+        assert_eq!(
+            Err(FromColumnsError::DuplicateKey),
+            SmallMap::<_, _, 3>::from_columns(smallvec![1, 1], smallvec!["seven", "one"])
+        );
+    }
+
+    #[test]
+    fn spill_allocates_room_for_inserted_key_value() {
+        fn test<const C: usize>() {
+            let mut map: SmallMap<_, _, C> = SmallMap::from_iter((0..C).map(|i| (i, i)));
+            assert!(map.is_inline());
+            map.insert(C, C);
+            assert!(!map.is_inline());
+            // Sized once for all key-values, not grown by the insert.
+            assert_eq!(C + 1, map.capacity());
+        }
+        test::<3>();
+        test::<7>();
+        test::<14>();
+        test::<28>();
+    }
+
+    #[test]
+    fn heap_variant_does_not_grow_inline_size() {
+        // The boxed heap variant only adds a pointer (and the enum tag) to the
+        // size of the inline buffer, next to the downgrade threshold and the
+        // counters of the `stats` feature.
+        let stats_size = if cfg!(feature = "stats") {
+            2 * mem::size_of::<usize>()
+        } else {
+            0
+        };
+        assert!(
+            mem::size_of::<SmallMap<u8, u8, 1>>() < mem::size_of::<IndexMap<u8, u8>>(),
+            "SmallMap with a tiny inline buffer is not smaller than IndexMap"
+        );
+        assert!(
+            mem::size_of::<SmallMap<usize, usize, 4>>()
+                <= mem::size_of::<SmallVec<[(usize, usize); 4]>>()
+                    + 2 * mem::size_of::<usize>()
+                    + stats_size
+        );
+    }
+
     #[test]
     fn debug_string_test() {
         let actual = format!("{:?}", smallmap_inline! {0=>6, 1=>5, 2=>4});
@@ -1582,11 +5916,13 @@ mod test {
     }
 
     #[test]
-    #[should_panic(
-        expected = "Cannot instantiate SmallMap with no inline capacity, use positive capacity or use IndexMap instead"
-    )]
-    fn new_fails_on_zero_capacity() {
-        SmallMap::<usize, usize, 0>::new();
+    fn debug_string_is_independent_of_storage() {
+        let inline: SmallMap<_, _, 3> = smallmap! {0 => 6, 1 => 5, 2 => 4};
+        let heap: SmallMap<_, _, 2> = smallmap! {0 => 6, 1 => 5, 2 => 4};
+        assert!(inline.is_inline());
+        assert!(!heap.is_inline());
+        assert_eq!(format!("{inline:?}"), format!("{heap:?}"));
+        assert_eq!(format!("{inline:#?}"), format!("{heap:#?}"));
     }
 
     #[test]
@@ -0,0 +1,216 @@
+use core::hash::BuildHasher;
+
+use indexmap::IndexMap;
+
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+
+use super::MapData;
+use super::SmallMap;
+
+/// Configures and creates a [`SmallMap`].
+///
+/// # Example
+///
+/// ```
+/// use more_collections::small_map::SmallMapBuilder;
+/// use more_collections::SmallMap;
+///
+/// // Move back inline as soon as the data fits.
+/// let mut map: SmallMap<usize, usize, 4> = SmallMapBuilder::new()
+///     .downgrade_ratio(1.0)
+///     .build();
+/// for i in 0..5 {
+///     map.insert(i, i);
+/// }
+/// assert!(!map.is_inline());
+/// map.pop();
+/// assert!(map.is_inline());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmallMapBuilder {
+    downgrade_ratio: f32,
+    heap: bool,
+}
+
+impl SmallMapBuilder {
+    /// Creates a builder for a map that is created inline and moves back
+    /// inline once its length drops to half the inline capacity, like
+    /// [`SmallMap::new`].
+    pub const fn new() -> Self {
+        Self {
+            downgrade_ratio: 0.5,
+            heap: false,
+        }
+    }
+
+    /// Sets the fraction of the inline capacity `C` at or below which the
+    /// length of a heap map must drop for the data to move back inline,
+    /// rounded down.
+    ///
+    /// The threshold applies whenever key-values are removed, including
+    /// [`SmallMap::clear`] and [`SmallMap::split_off`], and carries over to the
+    /// map returned by [`SmallMap::split_off`]. A ratio of `0.0` keeps the data
+    /// on the heap once it got there, a ratio of `1.0` moves it inline as soon
+    /// as it fits. [`SmallMap::compact`] and [`SmallMap::shrink_to`] ignore the
+    /// threshold and move the data inline whenever it fits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ratio` is not in the range `0.0..=1.0`.
+    pub fn downgrade_ratio(mut self, ratio: f32) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&ratio),
+            "downgrade ratio must be between 0 and 1"
+        );
+        self.downgrade_ratio = ratio;
+        self
+    }
+
+    /// Creates the map on the heap instead of inline.
+    pub fn on_heap(mut self) -> Self {
+        self.heap = true;
+        self
+    }
+
+    /// Creates an empty map with this configuration.
+    pub fn build<K, V, const C: usize, S>(self) -> SmallMap<K, V, C, S>
+    where
+        S: BuildHasher + Default,
+    {
+        let mut map = if self.heap {
+            SmallMap::from_data(MapData::Heap(Box::new(IndexMap::with_hasher(S::default()))))
+        } else {
+            SmallMap::from_data(MapData::Inline(Default::default()))
+        };
+        map.downgrade_below = if C > 0 && self.downgrade_ratio > 0.0 {
+            // Rounding down is intended, the ratio is at most 1 so the product
+            // is at most `C`.
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let threshold = (self.downgrade_ratio * C as f32) as usize;
+            threshold + 1
+        } else {
+            0
+        };
+        map
+    }
+}
+
+impl Default for SmallMapBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SmallMapBuilder;
+    use crate::SmallMap;
+
+    #[test]
+    fn zero_ratio_never_moves_inline() {
+        let mut map: SmallMap<_, _, 4> = SmallMapBuilder::new().downgrade_ratio(0.0).build();
+        for i in 0..5 {
+            map.insert(i, i);
+        }
+        assert!(!map.is_inline());
+        while map.pop().is_some() {
+            assert!(!map.is_inline());
+        }
+        map.retain(|_, _| false);
+        map.insert(0, 0);
+        map.remove(&0);
+        assert!(!map.is_inline());
+
+        for i in 0..5 {
+            map.insert(i, i);
+        }
+        let tail = map.split_off(1);
+        assert!(!map.is_inline());
+        assert!(map.iter_eq([(0, 0)]));
+        assert!(tail.iter_eq((1..5).map(|i| (i, i))));
+
+        map.clear();
+        assert!(map.is_empty());
+        assert!(!map.is_inline());
+
+        let mut map: SmallMap<u8, u8, 4> = SmallMapBuilder::new()
+            .on_heap()
+            .downgrade_ratio(0.0)
+            .build();
+        map.clear();
+        assert!(!map.is_inline());
+    }
+
+    #[test]
+    fn full_ratio_moves_inline_when_it_fits() {
+        let mut map: SmallMap<_, _, 4> = SmallMapBuilder::new().downgrade_ratio(1.0).build();
+        for i in 0..5 {
+            map.insert(i, i);
+        }
+        assert!(!map.is_inline());
+        map.remove(&2);
+        assert!(map.is_inline());
+        assert_eq!(4, map.len());
+
+        map.insert(2, 2);
+        assert!(!map.is_inline());
+        map.retain(|k, _| *k != 0);
+        assert!(map.is_inline());
+    }
+
+    #[test]
+    fn split_off_keeps_ratio() {
+        let mut map: SmallMap<_, _, 4> = SmallMapBuilder::new().downgrade_ratio(1.0).build();
+        for i in 0..12 {
+            map.insert(i, i);
+        }
+        let mut tail = map.split_off(6);
+        assert!(!tail.is_inline());
+        tail.truncate(4);
+        assert!(tail.is_inline());
+        assert!(tail.iter_eq((6..10).map(|i| (i, i))));
+
+        let mut map: SmallMap<_, _, 4> = SmallMapBuilder::new().downgrade_ratio(0.0).build();
+        for i in 0..12 {
+            map.insert(i, i);
+        }
+        let mut tail = map.split_off(6);
+        tail.truncate(1);
+        assert!(!tail.is_inline());
+    }
+
+    #[test]
+    fn default_ratio_matches_new() {
+        let mut built: SmallMap<_, _, 4> = SmallMapBuilder::default().build();
+        let mut new: SmallMap<_, _, 4> = SmallMap::new();
+        for map in [&mut built, &mut new] {
+            for i in 0..5 {
+                map.insert(i, i);
+            }
+            map.pop();
+            map.pop();
+            assert!(!map.is_inline());
+            map.pop();
+            assert!(map.is_inline());
+        }
+    }
+
+    #[test]
+    fn on_heap_creates_heap_map() {
+        let mut map: SmallMap<_, _, 4> = SmallMapBuilder::new()
+            .on_heap()
+            .downgrade_ratio(0.0)
+            .build();
+        assert!(!map.is_inline());
+        map.insert(0, 0);
+        assert!(!map.is_inline());
+        assert_eq!(Some(&0), map.get(&0));
+    }
+
+    #[test]
+    #[should_panic(expected = "downgrade ratio must be between 0 and 1")]
+    fn ratio_out_of_range_panics() {
+        let _ = SmallMapBuilder::new().downgrade_ratio(1.5);
+    }
+}
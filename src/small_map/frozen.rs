@@ -0,0 +1,153 @@
+use core::fmt;
+use core::fmt::Debug;
+use core::fmt::Formatter;
+use core::hash::BuildHasher;
+use core::hash::Hash;
+
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+#[cfg(not(feature = "std"))]
+use alloc::vec;
+use indexmap::Equivalent;
+
+use super::Iter;
+#[cfg(doc)]
+use super::SmallMap;
+use crate::collections::hash_map::RandomState;
+
+/// An immutable map that stores its key-values in a single boxed slice, in
+/// insertion order, created with [`SmallMap::into_frozen`].
+///
+/// Small maps are searched linearly. Larger maps also store an open addressing
+/// table of indices into the slice, which is kept at most half full so that
+/// lookups probe only a few slots.
+#[derive(Clone)]
+pub struct FrozenSmallMap<K, V, S = RandomState> {
+    entries: Box<[(K, V)]>,
+    // Index into `entries` plus one, `0` marks an empty slot. Empty if the
+    // key-values are searched linearly.
+    slots: Box<[usize]>,
+    hash_builder: S,
+}
+
+impl<K, V, S> FrozenSmallMap<K, V, S> {
+    /// Maps up to this length are searched linearly, without an index.
+    const LINEAR_SCAN_LEN: usize = 8;
+
+    /// The number of key-values stored in the map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns an iterator over the key-values in insertion order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter::Inline(self.entries.iter())
+    }
+}
+
+impl<K, V, S> FrozenSmallMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    // The caller must guarantee that the keys of `entries` are unique.
+    pub(super) fn from_unique_entries(entries: Box<[(K, V)]>, hash_builder: S) -> Self {
+        let mut map = Self {
+            entries,
+            slots: Box::default(),
+            hash_builder,
+        };
+        if map.len() > Self::LINEAR_SCAN_LEN {
+            let mut slots = vec![0; (map.len() * 2).next_power_of_two()].into_boxed_slice();
+            for (index, (key, _v)) in map.entries.iter().enumerate() {
+                let mut slot = map.first_slot(key, slots.len());
+                while slots[slot] != 0 {
+                    slot = (slot + 1) & (slots.len() - 1);
+                }
+                slots[slot] = index + 1;
+            }
+            map.slots = slots;
+        }
+        map
+    }
+
+    fn first_slot<Q: ?Sized + Hash>(&self, key: &Q, slots_len: usize) -> usize {
+        // Only the low bits of the hash are used, truncating it is intended.
+        #[allow(clippy::cast_possible_truncation)]
+        let hash = self.hash_builder.hash_one(key) as usize;
+        hash & (slots_len - 1)
+    }
+
+    /// Return a reference to the value stored for `key`, if it is present,
+    /// else `None`.
+    ///
+    /// Computational complexity:
+    ///  - up to 8 key-values: O(n)
+    ///  - otherwise: O(1)
+    pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K>,
+    {
+        if self.slots.is_empty() {
+            return self
+                .entries
+                .iter()
+                .find(|(k, _v)| key.equivalent(k))
+                .map(|(_k, v)| v);
+        }
+        let mut slot = self.first_slot(key, self.slots.len());
+        loop {
+            let (k, v) = self.entries.get(self.slots[slot].checked_sub(1)?)?;
+            if key.equivalent(k) {
+                return Some(v);
+            }
+            slot = (slot + 1) & (self.slots.len() - 1);
+        }
+    }
+}
+
+impl<K, V, S> Debug for FrozenSmallMap<K, V, S>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SmallMap;
+
+    #[test]
+    fn frozen_map_answers_the_same_lookups() {
+        fn test<const C: usize>(len: usize) {
+            let map: SmallMap<_, _, C> = (0..len).map(|i| (i * 7, i.to_string())).collect();
+            let frozen = map.clone().into_frozen();
+            assert_eq!(map.len(), frozen.len());
+            assert_eq!(map.is_empty(), frozen.is_empty());
+            assert!(frozen.iter().eq(map.iter()));
+            for key in 0..=len * 7 {
+                assert_eq!(map.get(&key), frozen.get(&key));
+            }
+        }
+        test::<4>(0);
+        test::<4>(3);
+        test::<4>(8);
+        test::<4>(9);
+        test::<4>(100);
+        test::<128>(100);
+    }
+
+    #[test]
+    fn frozen_map_debug_matches_source() {
+        let map: SmallMap<_, _, 2> = (0..20).map(|i| (i, i * 2)).collect();
+        assert_eq!(format!("{map:?}"), format!("{:?}", map.into_frozen()));
+    }
+}
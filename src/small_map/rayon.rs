@@ -0,0 +1,238 @@
+//! Parallel iterator types for [`SmallMap`] with
+//! [rayon](https://docs.rs/rayon/1.7/rayon).
+//!
+//! Inline data is processed in parallel over its slice of key-values, heap
+//! data uses the parallel iterators of `IndexMap`.
+//!
+//! You will rarely need to interact with this module directly unless you need
+//! to name one of the iterator types.
+
+use core::hash::BuildHasher;
+use core::hash::Hash;
+
+use indexmap::map::rayon as index_map_rayon;
+use indexmap::IndexMap;
+use rayon::iter::plumbing::Consumer;
+use rayon::iter::plumbing::ProducerCallback;
+use rayon::iter::plumbing::UnindexedConsumer;
+use rayon::iter::Either;
+use rayon::iter::FromParallelIterator;
+use rayon::iter::IndexedParallelIterator;
+use rayon::iter::IntoParallelIterator;
+use rayon::iter::IntoParallelRefIterator;
+use rayon::iter::IntoParallelRefMutIterator;
+use rayon::iter::ParallelExtend;
+use rayon::iter::ParallelIterator;
+
+use super::MapData;
+use super::SmallMap;
+
+type InlineParIter<'a, K, V> =
+    rayon::iter::Map<rayon::slice::Iter<'a, (K, V)>, fn(&'a (K, V)) -> (&'a K, &'a V)>;
+type InlineParIterMut<'a, K, V> =
+    rayon::iter::Map<rayon::slice::IterMut<'a, (K, V)>, fn(&'a mut (K, V)) -> (&'a K, &'a mut V)>;
+
+/// A parallel iterator over the key-values of a [`SmallMap`].
+pub struct ParIter<'a, K: Sync, V: Sync> {
+    inner: Either<InlineParIter<'a, K, V>, index_map_rayon::ParIter<'a, K, V>>,
+}
+
+/// A parallel iterator over the key-values of a [`SmallMap`], with mutable
+/// references to the values.
+pub struct ParIterMut<'a, K: Sync + Send, V: Send> {
+    inner: Either<InlineParIterMut<'a, K, V>, index_map_rayon::ParIterMut<'a, K, V>>,
+}
+
+/// A parallel owning iterator over the key-values of a [`SmallMap`].
+pub struct IntoParIter<K: Send, V: Send> {
+    inner: Either<rayon::vec::IntoIter<(K, V)>, index_map_rayon::IntoParIter<K, V>>,
+}
+
+impl<'a, K, V, const C: usize, S> IntoParallelIterator for &'a SmallMap<K, V, C, S>
+where
+    K: Sync,
+    V: Sync,
+{
+    type Item = (&'a K, &'a V);
+    type Iter = ParIter<'a, K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        let inner = match &self.data {
+            MapData::Inline(vec) => {
+                let f: fn(&'a (K, V)) -> (&'a K, &'a V) = |(k, v)| (k, v);
+                Either::Left(vec.as_slice().into_par_iter().map(f))
+            }
+            MapData::Heap(map) => Either::Right(map.par_iter()),
+        };
+        ParIter { inner }
+    }
+}
+
+impl<'a, K, V, const C: usize, S> IntoParallelIterator for &'a mut SmallMap<K, V, C, S>
+where
+    K: Sync + Send,
+    V: Send,
+{
+    type Item = (&'a K, &'a mut V);
+    type Iter = ParIterMut<'a, K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        let inner = match &mut self.data {
+            MapData::Inline(vec) => {
+                let f: fn(&'a mut (K, V)) -> (&'a K, &'a mut V) = |(k, v)| (&*k, v);
+                Either::Left(vec.as_mut_slice().into_par_iter().map(f))
+            }
+            MapData::Heap(map) => Either::Right(map.par_iter_mut()),
+        };
+        ParIterMut { inner }
+    }
+}
+
+impl<K, V, const C: usize, S> IntoParallelIterator for SmallMap<K, V, C, S>
+where
+    K: Send,
+    V: Send,
+{
+    type Item = (K, V);
+    type Iter = IntoParIter<K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        let inner = match self.data {
+            MapData::Inline(vec) => Either::Left(vec.into_vec().into_par_iter()),
+            MapData::Heap(map) => Either::Right((*map).into_par_iter()),
+        };
+        IntoParIter { inner }
+    }
+}
+
+macro_rules! delegate_parallel_iterator {
+    ([$($generics:tt)*] $ty:ty => $item:ty) => {
+        impl<$($generics)*> ParallelIterator for $ty {
+            type Item = $item;
+
+            fn drive_unindexed<C>(self, consumer: C) -> C::Result
+            where
+                C: UnindexedConsumer<Self::Item>,
+            {
+                self.inner.drive_unindexed(consumer)
+            }
+
+            fn opt_len(&self) -> Option<usize> {
+                Some(self.inner.len())
+            }
+        }
+
+        impl<$($generics)*> IndexedParallelIterator for $ty {
+            fn drive<C>(self, consumer: C) -> C::Result
+            where
+                C: Consumer<Self::Item>,
+            {
+                self.inner.drive(consumer)
+            }
+
+            fn len(&self) -> usize {
+                self.inner.len()
+            }
+
+            fn with_producer<CB>(self, callback: CB) -> CB::Output
+            where
+                CB: ProducerCallback<Self::Item>,
+            {
+                self.inner.with_producer(callback)
+            }
+        }
+    };
+}
+
+delegate_parallel_iterator!(['a, K: Sync, V: Sync] ParIter<'a, K, V> => (&'a K, &'a V));
+delegate_parallel_iterator!(['a, K: Sync + Send, V: Send] ParIterMut<'a, K, V> => (&'a K, &'a mut V));
+delegate_parallel_iterator!([K: Send, V: Send] IntoParIter<K, V> => (K, V));
+
+/// Collects the key-values into an `IndexMap` in parallel, and moves them
+/// inline if they fit in the inline capacity.
+impl<K, V, const C: usize, S> FromParallelIterator<(K, V)> for SmallMap<K, V, C, S>
+where
+    K: Hash + Eq + Send,
+    V: Send,
+    S: BuildHasher + Default + Send,
+{
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        Self::from_map(IndexMap::from_par_iter(par_iter))
+    }
+}
+
+/// Heap maps are extended in parallel. Inline maps collect the key-values in
+/// parallel and insert them in order, moving to the heap if needed.
+impl<K, V, const C: usize, S> ParallelExtend<(K, V)> for SmallMap<K, V, C, S>
+where
+    K: Hash + Eq + Send,
+    V: Send,
+    S: BuildHasher + Default + Send,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        match &mut self.data {
+            MapData::Inline(_) => {
+                let entries: Vec<(K, V)> = par_iter.into_par_iter().collect();
+                for (key, value) in entries {
+                    self.insert(key, value);
+                }
+            }
+            MapData::Heap(map) => map.par_extend(par_iter),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn parallel_iterators_match_sequential() {
+        fn test<const C: usize>(inline: bool) {
+            let mut map: SmallMap<_, _, C> = SmallMap::from_iter((0..100).map(|i| (i, i * 3)));
+            assert_eq!(inline, map.is_inline());
+            let sum: usize = map.values().sum();
+
+            assert_eq!(sum, map.par_iter().map(|(_k, v)| v).sum());
+            assert_eq!(
+                map.iter().collect::<Vec<_>>(),
+                map.par_iter().collect::<Vec<_>>()
+            );
+
+            map.par_iter_mut().for_each(|(_k, v)| *v *= 2);
+            assert_eq!(2 * sum, map.values().sum());
+
+            assert_eq!(2 * sum, map.into_par_iter().map(|(_k, v)| v).sum());
+        }
+        test::<8>(false);
+        test::<100>(true);
+    }
+
+    #[test]
+    fn collect_and_extend_in_parallel() {
+        fn test<const C: usize>(len: usize, inline: bool) {
+            let expected = SmallMap::<_, _, C>::from_iter((0..len).map(|i| (i % 500, i)));
+            let map: SmallMap<_, _, C> = (0..len).into_par_iter().map(|i| (i % 500, i)).collect();
+            assert_eq!(inline, map.is_inline());
+            assert_eq!(expected.len(), map.len());
+            assert!(expected.iter().all(|(k, _v)| map.contains_key(k)));
+
+            let mut map: SmallMap<_, _, C> = SmallMap::new();
+            map.par_extend((0..len).into_par_iter().map(|i| (i, i * 2)));
+            assert!(map.iter_eq((0..len).map(|i| (i, i * 2))));
+            map.par_extend((0..len).into_par_iter().map(|i| (i + len, i)));
+            assert_eq!(2 * len, map.len());
+            assert!((0..len).all(|i| map.get(&(i + len)) == Some(&i)));
+        }
+        test::<8>(10_000, false);
+        test::<8>(4, true);
+    }
+}
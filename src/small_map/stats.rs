@@ -0,0 +1,107 @@
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+
+/// Counters collected over the lifetime of a `SmallMap`, see
+/// [`SmallMap::stats`](super::SmallMap::stats).
+///
+/// Frequent spills or many inline scans on large maps suggest that the inline
+/// capacity `C` does not fit the workload.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SmallMapStats {
+    /// The number of times the data was moved from inline to the heap.
+    pub spills: usize,
+    /// The number of linear scans over the inline data to find a key.
+    pub inline_scans: usize,
+}
+
+// Lookups only borrow the map, so the counters are atomics to keep `SmallMap`
+// `Sync`.
+#[derive(Debug, Default)]
+pub(super) struct Counters {
+    spills: AtomicUsize,
+    inline_scans: AtomicUsize,
+}
+
+impl Counters {
+    pub(super) const fn new() -> Self {
+        Self {
+            spills: AtomicUsize::new(0),
+            inline_scans: AtomicUsize::new(0),
+        }
+    }
+
+    pub(super) fn record_spill(&self) {
+        self.spills.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_inline_scan(&self) {
+        self.inline_scans.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn snapshot(&self) -> SmallMapStats {
+        SmallMapStats {
+            spills: self.spills.load(Ordering::Relaxed),
+            inline_scans: self.inline_scans.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Clone for Counters {
+    fn clone(&self) -> Self {
+        let stats = self.snapshot();
+        Self {
+            spills: AtomicUsize::new(stats.spills),
+            inline_scans: AtomicUsize::new(stats.inline_scans),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::smallmap;
+    use crate::SmallMap;
+
+    #[test]
+    fn spills_are_counted_once_when_crossing_inline_capacity() {
+        let mut map: SmallMap<_, _, 2> = smallmap! { 0 => 'a', 1 => 'b' };
+        assert_eq!(0, map.stats().spills);
+
+        map.insert(2, 'c');
+        map.insert(3, 'd');
+        assert!(!map.is_inline());
+        assert_eq!(1, map.stats().spills);
+
+        map.retain(|k, _v| *k == 0);
+        assert!(map.is_inline());
+        map.insert(1, 'b');
+        map.insert(2, 'c');
+        assert_eq!(2, map.stats().spills);
+    }
+
+    #[test]
+    fn inline_scans_are_counted_only_inline() {
+        let mut map: SmallMap<_, _, 2> = SmallMap::new();
+        map.insert(0, 'a');
+        assert_eq!(Some(&'a'), map.get(&0));
+        assert!(map.contains_key(&0));
+        assert_eq!(
+            SmallMapStats {
+                spills: 0,
+                inline_scans: 3
+            },
+            map.stats()
+        );
+
+        map.insert(1, 'b');
+        map.insert(2, 'c');
+        assert_eq!(Some(&'c'), map.get(&2));
+        assert_eq!(
+            SmallMapStats {
+                spills: 1,
+                inline_scans: 5
+            },
+            map.clone().stats()
+        );
+    }
+}
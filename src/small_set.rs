@@ -1,18 +1,31 @@
 use crate::collections::hash_map::RandomState;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::cmp::Ordering;
 use core::fmt;
 use core::fmt::Debug;
 use core::fmt::Formatter;
 use core::hash::BuildHasher;
 use core::iter::Chain;
 use core::iter::FusedIterator;
+use core::marker::PhantomData;
+use core::ops::BitAndAssign;
+use core::ops::BitOrAssign;
+use core::ops::BitXorAssign;
+use core::ops::SubAssign;
 
 use ::core::hash::Hash;
 use indexmap::Equivalent;
+use indexmap::IndexMap;
+use indexmap::IndexSet;
 use smallvec::SmallVec;
 
 use crate::small_map;
 use crate::SmallMap;
 
+#[cfg(feature = "rayon")]
+mod rayon;
+
 /// A set-like container that can store a specified number of elements inline.
 ///
 /// `SmallSet` shares most of its API with, and behaves like,
@@ -26,6 +39,9 @@ use crate::SmallMap;
 /// exceed `C` _most of the time_ but it still needs to support cases where the
 /// data _does_ exceed `C`.
 ///
+/// Like [`SmallMap`], data on the heap only moves back inline once its length
+/// has dropped to the downgrade threshold, half the inline capacity by default.
+///
 /// # Example
 ///
 /// ```
@@ -89,6 +105,25 @@ impl<T, const C: usize, S> SmallSet<T, C, S> {
         self.data.is_inline()
     }
 
+    /// Keeps the first `len` values and drops the rest. Does nothing if `len`
+    /// is greater than or equal to the length of the set.
+    ///
+    /// Like [`SmallMap::truncate`], the data is moved inline if the remaining
+    /// values fit the downgrade threshold.
+    pub fn truncate(&mut self, len: usize) {
+        self.data.truncate(len);
+    }
+
+    /// Removes all values from the set.
+    ///
+    /// Like [`SmallMap::clear`], the data is moved inline, so a set that was on
+    /// the heap releases its heap allocation. A set without inline capacity, or
+    /// one built with a downgrade ratio of `0.0`, stays on the heap and keeps
+    /// its capacity.
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
     /// Returns an iterator over the values in insertion order.
     pub fn iter(&'_ self) -> Iter<'_, T> {
         Iter {
@@ -96,6 +131,11 @@ impl<T, const C: usize, S> SmallSet<T, C, S> {
         }
     }
 
+    /// Convert the set into a `Vec` of values, in insertion order.
+    pub fn into_vec(self) -> Vec<T> {
+        self.into_iter().collect()
+    }
+
     // Helper method for macro, don't use directly.
     #[doc(hidden)]
     pub const fn from_const_unchecked_with_hasher(inline: SmallVec<[(T, ()); C]>) -> Self {
@@ -110,6 +150,19 @@ where
     T: Hash + Eq,
     S: BuildHasher + Default,
 {
+    /// Removes all values from the set and yields them, in insertion order.
+    ///
+    /// The set is left empty like after [`SmallSet::clear`], even if the
+    /// iterator is not consumed. A set without inline capacity, or one built
+    /// with a downgrade ratio of `0.0`, stays on the heap, but its heap
+    /// allocation moves to the iterator.
+    pub fn drain(&mut self) -> Drain<'_, T, C> {
+        Drain {
+            inner: self.data.take_data().into_iter(),
+            marker: PhantomData,
+        }
+    }
+
     /// Inserts the specified value into this set.
     ///
     /// If an equivalent item already exists in the set, it returns `false`
@@ -124,7 +177,45 @@ where
     ///  - inline: O(n)
     ///  - heap: O(1)
     pub fn insert(&mut self, value: T) -> bool {
-        self.data.insert(value, ()).is_some()
+        self.insert_full(value).1
+    }
+
+    /// Inserts the specified value into this set, replacing an equivalent
+    /// value that is already stored.
+    ///
+    /// Returns the replaced value, or `None` if there was no equivalent value,
+    /// in which case `value` is inserted at the end. A replaced value keeps its
+    /// position in the insertion order.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn replace(&mut self, value: T) -> Option<T> {
+        self.data.replace_key(value, ())
+    }
+
+    /// Return a reference to the value equivalent to `value`, inserting the
+    /// value returned by `f` if there is none.
+    ///
+    /// `f` is only called if no equivalent value is present, and it must
+    /// return a value equivalent to `value`. Only a single lookup is performed
+    /// unless the insertion moves the data to the heap.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn get_or_insert_with<Q: ?Sized, F>(&mut self, value: &Q, f: F) -> &T
+    where
+        Q: Hash + Equivalent<T>,
+        F: FnOnce(&Q) -> T,
+    {
+        let index = self
+            .data
+            .get_index_of_or_insert_with(value, |value| (f(value), ()));
+        self.data
+            .get_index(index)
+            .map(|(v, _)| v)
+            .expect("value is present")
     }
 
     /// Inserts the specified value into this set, and get their index.
@@ -142,8 +233,51 @@ where
     ///  - inline: O(n)
     ///  - heap: O(1)
     pub fn insert_full(&mut self, value: T) -> (usize, bool) {
-        let (index, value) = self.data.insert_full(value, ());
-        (index, value.is_some())
+        let (index, existing) = self.data.insert_full(value, ());
+        (index, existing.is_none())
+    }
+
+    /// Removes the values that are in `other` and adds the values of `other`
+    /// that are not in `self`, cloning them.
+    ///
+    /// The remaining values of `self` keep their order and are followed by the
+    /// added values, in the order of `other`. If the result fits the downgrade
+    /// threshold, it is moved inline.
+    ///
+    /// Computational complexity: O(n + m) lookups
+    pub fn symmetric_difference_in_place<const C2: usize, S2>(
+        &mut self,
+        other: &SmallSet<T, C2, S2>,
+    ) where
+        T: Clone,
+        S2: BuildHasher,
+    {
+        let len = self.len();
+        for value in other.iter() {
+            if !self.contains(value) {
+                self.insert(value.clone());
+            }
+        }
+        // Only the original values can be in `other`, the added ones are kept.
+        let mut index = 0;
+        self.retain(|value| {
+            index += 1;
+            index > len || !other.contains(value)
+        });
+    }
+
+    /// Inserts the specified value into this set if it is absent, and returns
+    /// its index.
+    ///
+    /// If an equivalent item already exists in the set, its index is returned
+    /// and the original value is left in the set. Indices are preserved when
+    /// the data is moved to the heap, so they can be used to intern values.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn insert_or_index(&mut self, value: T) -> usize {
+        self.insert_full(value).0
     }
 }
 
@@ -156,6 +290,27 @@ where
         SmallSet { data: map }
     }
 
+    /// Convert the specified set into a `SmallSet`, preserving order.
+    ///
+    /// If the set len is smaller or equal the inline capacity, the data will be
+    /// moved inline. Otherwise it is moved to a heap map that uses a clone of
+    /// the set's hasher.
+    pub fn from_index_set(set: IndexSet<T, S>) -> Self
+    where
+        S: Clone,
+    {
+        let mut map = IndexMap::with_capacity_and_hasher(set.len(), set.hasher().clone());
+        map.extend(set.into_iter().map(|value| (value, ())));
+        Self::from_keys(SmallMap::from_map(map))
+    }
+
+    /// Convert the set into the underlying map with `()` values, preserving
+    /// order and whether the data is stored inline. This is the inverse of
+    /// [`SmallSet::from_keys`].
+    pub fn into_map(self) -> SmallMap<T, (), C, S> {
+        self.data
+    }
+
     /// Get a value by index, if it is present, else `None`.
     ///
     /// Computational complexity: O(1)
@@ -190,6 +345,119 @@ where
         self.data.remove(key).is_some()
     }
 
+    /// Removes the last value in insertion order and returns it, or `None` if
+    /// the set is empty.
+    ///
+    /// Computational complexity: O(1)
+    pub fn pop(&mut self) -> Option<T> {
+        self.data.pop().map(|(k, _v)| k)
+    }
+
+    /// Removes the value at `index`, shifting all following values to preserve
+    /// order, and returns it together with the new length of the set, or
+    /// `None` if `index` is out of bounds.
+    ///
+    /// Computational complexity: O(n)
+    pub fn pop_index(&mut self, index: usize) -> Option<(T, usize)> {
+        self.data
+            .pop_index(index)
+            .map(|((value, _), len)| (value, len))
+    }
+
+    /// Retains only the values for which `keep` returns `true`, in order, and
+    /// removes all others.
+    ///
+    /// Like [`SmallMap::retain`], the data is moved inline if the remaining
+    /// values fit the downgrade threshold.
+    ///
+    /// Computational complexity: O(n)
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.data.retain(|v, _| keep(v));
+    }
+
+    /// Returns an iterator that removes and yields the values for which `pred`
+    /// returns `true`, in order.
+    ///
+    /// The order of the remaining values is preserved. If the iterator is
+    /// dropped before it is exhausted, the values that were not yet visited are
+    /// kept. Once the iterator is dropped, the data is moved inline if it fits
+    /// the downgrade threshold.
+    ///
+    /// Computational complexity: O(n) per removed value
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, T, C, S, F>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        ExtractIf {
+            data: &mut self.data,
+            index: 0,
+            pred,
+        }
+    }
+
+    /// Sorts the set in place.
+    ///
+    /// The sort is stable. Positional indices, such as those returned by
+    /// [`get_index_of`](Self::get_index_of), reflect the new order.
+    pub fn sort(&mut self)
+    where
+        T: Ord,
+    {
+        self.data.sort_keys();
+    }
+
+    /// Sorts the set in place using the comparison function `cmp`.
+    ///
+    /// The sort is stable. Positional indices reflect the new order.
+    pub fn sort_by<F>(&mut self, mut cmp: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        self.data.sort_by(|a, _, b, _| cmp(a, b));
+    }
+
+    /// Binary searches this set for `value`, assuming the set is sorted, for
+    /// example with [`sort`](Self::sort).
+    ///
+    /// If the value is found then [`Result::Ok`] is returned, containing its
+    /// index. Otherwise [`Result::Err`] is returned, containing the index
+    /// where the value could be inserted while maintaining sorted order. If
+    /// the set is not sorted, the result is unspecified.
+    ///
+    /// Computational complexity: O(log n)
+    pub fn binary_search(&self, value: &T) -> Result<usize, usize>
+    where
+        T: Ord,
+    {
+        self.data.binary_search_keys(value)
+    }
+
+    /// Reverses the order of the values in place.
+    ///
+    /// Computational complexity: O(n)
+    pub fn reverse(&mut self) {
+        self.data.reverse();
+    }
+
+    /// Remove the value equivalent to `value` and return the stored value, or
+    /// `None` if it is not present.
+    ///
+    /// **NOTE:** Like [`SmallSet::remove`], this is equivalent to
+    /// `.swap_remove(value)` and does not preserve the order of the values.
+    ///
+    /// Computational complexity:
+    ///  - inline: O(n)
+    ///  - heap: O(1)
+    pub fn take<Q: ?Sized>(&mut self, value: &Q) -> Option<T>
+    where
+        Q: Hash + Equivalent<T>,
+    {
+        self.data.swap_remove_full(value).map(|(_i, v, _)| v)
+    }
+
     /// Return an iterator over the values that are in `self` but not `other`.
     ///
     /// Values are produced in the same order that they appear in `self`.
@@ -268,6 +536,33 @@ where
     {
         self.data.contains_key(value)
     }
+
+    /// Removes all values that are in `other`, keeping the order of the
+    /// remaining values.
+    ///
+    /// If the remaining data fits the downgrade threshold, it is moved inline.
+    ///
+    /// Computational complexity: O(n) lookups in `other`
+    pub fn difference_in_place<const C2: usize, S2>(&mut self, other: &SmallSet<T, C2, S2>)
+    where
+        S2: BuildHasher,
+    {
+        if !other.is_empty() {
+            self.retain(|value| !other.contains(value));
+        }
+    }
+
+    /// Retains only the values that are also in `other`, keeping their order.
+    ///
+    /// If the remaining data fits the downgrade threshold, it is moved inline.
+    ///
+    /// Computational complexity: O(n) lookups in `other`
+    pub fn retain_in<const C2: usize, S2>(&mut self, other: &SmallSet<T, C2, S2>)
+    where
+        S2: BuildHasher,
+    {
+        self.retain(|value| other.contains(value));
+    }
 }
 
 impl<T, const C: usize, S> Hash for SmallSet<T, C, S>
@@ -288,6 +583,31 @@ where
     }
 }
 
+/// Sets are compared lexicographically by their values in insertion order.
+///
+/// This is consistent with [`PartialEq`], which also takes insertion order into
+/// account: sets with the same values in a different order are not equal, and
+/// do not compare as [`Ordering::Equal`]. Sort both sets first for an
+/// order-independent comparison.
+impl<T, const C: usize, S> PartialOrd for SmallSet<T, C, S>
+where
+    T: Hash + Eq + PartialOrd,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+/// See [`PartialOrd`] for how sets are ordered.
+impl<T, const C: usize, S> Ord for SmallSet<T, C, S>
+where
+    T: Hash + Ord,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
 #[derive(Clone)]
 pub struct Iter<'a, T> {
     inner: small_map::Iter<'a, T, ()>,
@@ -313,6 +633,8 @@ impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
     }
 }
 
+impl<'a, T> FusedIterator for Iter<'a, T> {}
+
 impl<T, const C: usize, S> IntoIterator for SmallSet<T, C, S> {
     type Item = T;
 
@@ -343,8 +665,83 @@ impl<T, const C: usize> ExactSizeIterator for IntoIter<T, C> {
     }
 }
 
+impl<T, const C: usize> DoubleEndedIterator for IntoIter<T, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| k)
+    }
+}
+
 impl<T, const C: usize> FusedIterator for IntoIter<T, C> {}
 
+/// A draining iterator over the values of a [`SmallSet`], see
+/// [`SmallSet::drain`].
+pub struct Drain<'a, T, const C: usize> {
+    inner: small_map::IntoIter<T, (), C>,
+    marker: PhantomData<&'a mut T>,
+}
+
+impl<T, const C: usize> Iterator for Drain<'_, T, C> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+}
+
+impl<T, const C: usize> ExactSizeIterator for Drain<'_, T, C> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl<T, const C: usize> DoubleEndedIterator for Drain<'_, T, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<T, const C: usize> FusedIterator for Drain<'_, T, C> {}
+
+/// An iterator that removes and yields the values of a [`SmallSet`] that
+/// match a predicate, see [`SmallSet::extract_if`].
+pub struct ExtractIf<'a, T, const C: usize, S, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    data: &'a mut SmallMap<T, (), C, S>,
+    index: usize,
+    pred: F,
+}
+
+impl<T, const C: usize, S, F> Iterator for ExtractIf<'_, T, C, S, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pred = &mut self.pred;
+        self.data
+            .extract_next(&mut self.index, &mut |v, _| pred(v))
+            .map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.data.len() - self.index))
+    }
+}
+
+impl<T, const C: usize, S, F> FusedIterator for ExtractIf<'_, T, C, S, F> where F: FnMut(&T) -> bool {}
+
+impl<T, const C: usize, S, F> Drop for ExtractIf<'_, T, C, S, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    fn drop(&mut self) {
+        self.data.downgrade_if_small();
+    }
+}
+
 impl<T, const C: usize, S> FromIterator<T> for SmallSet<T, C, S>
 where
     T: Hash + Eq,
@@ -357,6 +754,85 @@ where
     }
 }
 
+impl<T, const C: usize, S> Extend<T> for SmallSet<T, C, S>
+where
+    T: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|value| {
+            self.insert(value);
+        });
+    }
+}
+
+impl<'a, T, const C: usize, S> Extend<&'a T> for SmallSet<T, C, S>
+where
+    T: Hash + Eq + Copy + 'a,
+    S: BuildHasher + Default,
+{
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+/// Adds the values of `other` that are not in `self`, in order, cloning them.
+impl<T, const C: usize, S, const C2: usize, S2> BitOrAssign<&SmallSet<T, C2, S2>>
+    for SmallSet<T, C, S>
+where
+    T: Hash + Eq + Clone,
+    S: BuildHasher + Default,
+    S2: BuildHasher,
+{
+    fn bitor_assign(&mut self, other: &SmallSet<T, C2, S2>) {
+        for value in other.iter() {
+            self.get_or_insert_with(value, T::clone);
+        }
+    }
+}
+
+/// Retains only the values that are also in `other`, see
+/// [`SmallSet::retain_in`].
+impl<T, const C: usize, S, const C2: usize, S2> BitAndAssign<&SmallSet<T, C2, S2>>
+    for SmallSet<T, C, S>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    S2: BuildHasher,
+{
+    fn bitand_assign(&mut self, other: &SmallSet<T, C2, S2>) {
+        self.retain_in(other);
+    }
+}
+
+/// Keeps the values that are in `self` or `other` but not in both, see
+/// [`SmallSet::symmetric_difference_in_place`].
+impl<T, const C: usize, S, const C2: usize, S2> BitXorAssign<&SmallSet<T, C2, S2>>
+    for SmallSet<T, C, S>
+where
+    T: Hash + Eq + Clone,
+    S: BuildHasher + Default,
+    S2: BuildHasher,
+{
+    fn bitxor_assign(&mut self, other: &SmallSet<T, C2, S2>) {
+        self.symmetric_difference_in_place(other);
+    }
+}
+
+/// Removes the values that are in `other`, see
+/// [`SmallSet::difference_in_place`].
+impl<T, const C: usize, S, const C2: usize, S2> SubAssign<&SmallSet<T, C2, S2>>
+    for SmallSet<T, C, S>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    S2: BuildHasher,
+{
+    fn sub_assign(&mut self, other: &SmallSet<T, C2, S2>) {
+        self.difference_in_place(other);
+    }
+}
+
 impl<T, const C: usize, S> Debug for SmallSet<T, C, S>
 where
     T: Hash + Eq + Debug,
@@ -584,6 +1060,7 @@ macro_rules! smallset_inline {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::small_map::SmallMapBuilder;
 
     #[test]
     fn test_len_and_inline_capacity() {
@@ -628,6 +1105,16 @@ mod test {
                 inline_map.iter().collect::<Vec<_>>(),
                 "iter() does not return values in the correct order"
             );
+            assert_eq!(
+                vec![&4, &0, &1],
+                inline_map.iter().rev().collect::<Vec<_>>(),
+                "iter().rev() does not return values in the correct order"
+            );
+            assert_eq!(
+                vec![4, 0, 1],
+                inline_map.clone().into_iter().rev().collect::<Vec<_>>(),
+                "into_iter().rev() does not return values in the correct order"
+            );
             assert_eq!(
                 vec![1, 0, 4],
                 inline_map.into_iter().collect::<Vec<_>>(),
@@ -638,6 +1125,403 @@ mod test {
         test::<3>(true);
     }
 
+    #[test]
+    fn iterators_are_fused() {
+        fn test<const C: usize>(inline: bool) {
+            let set: SmallSet<_, C> = smallset! { 1, 0 };
+            assert_eq!(inline, set.is_inline());
+
+            let mut iter = set.iter();
+            while iter.next().is_some() {}
+            assert_eq!(None, iter.next());
+            assert_eq!(None, iter.next());
+
+            let mut iter = set.into_iter();
+            while iter.next().is_some() {}
+            assert_eq!(None, iter.next());
+            assert_eq!(None, iter.next());
+        }
+        test::<1>(false);
+        test::<2>(true);
+    }
+
+    #[test]
+    fn cloned_iterator_continues_independently() {
+        fn test<const C: usize>(inline: bool) {
+            let set: SmallSet<_, C> = smallset! { 1, 0, 4 };
+            assert_eq!(inline, set.is_inline());
+
+            let mut iter = set.iter();
+            assert_eq!(Some(&1), iter.next());
+            let lookahead = iter.clone();
+            assert_eq!(vec![&0, &4], lookahead.collect::<Vec<_>>());
+            assert_eq!(vec![&0, &4], iter.collect::<Vec<_>>());
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn pop_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut set: SmallSet<_, C> = smallset! { 1, 0, 4 };
+            assert_eq!(inline, set.is_inline());
+            assert_eq!(Some(4), set.pop());
+            assert_eq!(Some(0), set.pop());
+            assert_eq!(Some(1), set.pop());
+            assert_eq!(None, set.pop());
+            assert!(set.is_empty());
+            assert!(set.is_inline());
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn pop_index_test() {
+        let mut set: SmallSet<_, 4> = SmallSet::from_iter(0..6);
+        assert!(!set.is_inline());
+        assert_eq!(Some((3, 5)), set.pop_index(3));
+        assert_eq!(Some((1, 4)), set.pop_index(1));
+        assert_eq!(None, set.pop_index(4));
+        assert_eq!(vec![&0, &2, &4, &5], set.iter().collect::<Vec<_>>());
+        assert_eq!(Some((4, 3)), set.pop_index(2));
+        assert_eq!(Some((0, 2)), set.pop_index(0));
+        assert_eq!(vec![&2, &5], set.iter().collect::<Vec<_>>());
+        assert!(set.is_inline());
+    }
+
+    #[test]
+    fn get_or_insert_with_only_calls_f_when_vacant() {
+        fn test<const C: usize>(inline: bool) {
+            let mut set: SmallSet<String, C> = smallset! { "a".to_string(), "b".to_string() };
+            let mut calls = 0;
+
+            let value = set.get_or_insert_with("b", |v| {
+                calls += 1;
+                v.to_string()
+            });
+            assert_eq!("b", value);
+            assert_eq!(0, calls);
+            assert_eq!(2, set.len());
+
+            let value = set.get_or_insert_with("c", |v| {
+                calls += 1;
+                v.to_string()
+            });
+            assert_eq!("c", value);
+            assert_eq!(1, calls);
+            assert_eq!(Some(2), set.get_index_of("c"));
+            assert_eq!(inline, set.is_inline());
+        }
+        test::<2>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn insert_or_index_interns_values() {
+        fn test<const C: usize>(inline: bool) {
+            let mut set: SmallSet<&str, C> = SmallSet::new();
+            assert_eq!(0, set.insert_or_index("a"));
+            assert_eq!(1, set.insert_or_index("b"));
+            assert_eq!(0, set.insert_or_index("a"));
+            assert_eq!(2, set.insert_or_index("c"));
+            assert_eq!(1, set.insert_or_index("b"));
+            assert_eq!(3, set.insert_or_index("d"));
+            assert_eq!(2, set.insert_or_index("c"));
+            assert_eq!(4, set.len());
+            assert_eq!(inline, set.is_inline());
+            for (index, value) in ["a", "b", "c", "d"].into_iter().enumerate() {
+                assert_eq!(index, set.insert_or_index(value));
+            }
+        }
+        test::<2>(false);
+        test::<4>(true);
+    }
+
+    #[test]
+    fn truncate_test() {
+        let mut set: SmallSet<_, 4> = SmallSet::from_iter(0..8);
+        assert!(!set.is_inline());
+
+        set.truncate(3);
+        assert_eq!(vec![&0, &1, &2], set.iter().collect::<Vec<_>>());
+        assert!(!set.is_inline());
+
+        set.truncate(2);
+        assert_eq!(vec![&0, &1], set.iter().collect::<Vec<_>>());
+        assert!(set.is_inline());
+    }
+
+    #[test]
+    fn from_index_set_test() {
+        let input = IndexSet::from([3, 1, 4]);
+
+        let set = SmallSet::<_, 3>::from_index_set(input.clone());
+        assert!(set.is_inline());
+        assert_eq!(vec![&3, &1, &4], set.iter().collect::<Vec<_>>());
+
+        let set = SmallSet::<_, 2>::from_index_set(input);
+        assert!(!set.is_inline());
+        assert_eq!(vec![&3, &1, &4], set.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn extend_moves_data_to_heap() {
+        let mut set: SmallSet<_, 3> = smallset! { 1, 0 };
+        set.extend([0, 4, 1]);
+        assert_eq!(vec![&1, &0, &4], set.iter().collect::<Vec<_>>());
+        assert!(set.is_inline());
+
+        set.extend(&[5, 4, 6]);
+        assert_eq!(vec![&1, &0, &4, &5, &6], set.iter().collect::<Vec<_>>());
+        assert!(!set.is_inline());
+    }
+
+    #[test]
+    fn into_map_round_trips_through_from_keys() {
+        fn test<const C: usize>(inline: bool) {
+            let set: SmallSet<_, C> = smallset! { 1, 0, 4 };
+            let map = set.clone().into_map();
+            assert_eq!(inline, map.is_inline());
+            assert!(map.iter_eq([(1, ()), (0, ()), (4, ())]));
+            assert_eq!(set, SmallSet::from_keys(map));
+        }
+        test::<2>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn drain_moves_data_inline() {
+        let mut set: SmallSet<_, 2> = smallset! { 1, 0, 4 };
+        assert!(!set.is_inline());
+        let drained: Vec<_> = set.drain().collect();
+        assert_eq!(vec![1, 0, 4], drained);
+        assert!(set.is_empty());
+        assert!(set.is_inline());
+
+        set.extend([5, 6]);
+        drop(set.drain());
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn drain_keeps_downgrade_threshold() {
+        let map: SmallMap<_, (), 2> = SmallMapBuilder::new().downgrade_ratio(0.0).build();
+        let mut set = SmallSet::from_keys(map);
+        set.extend([1, 0, 4]);
+        assert!(!set.is_inline());
+        assert_eq!(vec![1, 0, 4], set.drain().collect::<Vec<_>>());
+        assert!(set.is_empty());
+        assert!(!set.is_inline());
+
+        set.extend([5, 6, 7]);
+        set.remove(&5);
+        set.remove(&6);
+        assert!(!set.is_inline());
+
+        let mut set: SmallSet<_, 0> = smallset! { 1, 0 };
+        assert!(!set.is_inline());
+        assert_eq!(vec![1, 0], set.drain().collect::<Vec<_>>());
+        assert!(set.is_empty());
+        assert!(!set.is_inline());
+        set.insert(2);
+        assert_eq!(vec![&2], set.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn clear_moves_data_inline() {
+        let mut set: SmallSet<_, 2> = smallset! { 1, 0, 4 };
+        assert!(!set.is_inline());
+        set.clear();
+        assert!(set.is_empty());
+        assert!(set.is_inline());
+        set.insert(7);
+        assert_eq!(vec![&7], set.iter().collect::<Vec<_>>());
+        assert!(set.is_inline());
+    }
+
+    #[test]
+    fn clear_keeps_heap_without_downgrade() {
+        let map: SmallMap<_, (), 2> = SmallMapBuilder::new().downgrade_ratio(0.0).build();
+        let mut set = SmallSet::from_keys(map);
+        set.extend([1, 0, 4]);
+        set.clear();
+        assert!(set.is_empty());
+        assert!(!set.is_inline());
+
+        let mut set: SmallSet<_, 0> = smallset! { 1, 0 };
+        set.clear();
+        assert!(set.is_empty());
+        assert!(!set.is_inline());
+    }
+
+    #[test]
+    fn retain_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut set: SmallSet<_, C> = SmallSet::from_iter(0..6);
+            assert_eq!(inline, set.is_inline());
+            set.retain(|v| v % 2 == 1);
+            assert_eq!(vec![&1, &3, &5], set.iter().collect::<Vec<_>>());
+        }
+        test::<4>(false);
+        test::<6>(true);
+    }
+
+    #[test]
+    fn extract_if_removes_odd_values_from_heap_set() {
+        let mut set: SmallSet<_, 4> = SmallSet::from_iter(0..10);
+        assert!(!set.is_inline());
+
+        let odd = set.extract_if(|v| v % 2 == 1).collect::<Vec<_>>();
+        assert_eq!(vec![1, 3, 5, 7, 9], odd);
+        assert_eq!(vec![&0, &2, &4, &6, &8], set.iter().collect::<Vec<_>>());
+        assert!(!set.is_inline());
+        assert!(set.contains(&4));
+        assert!(!set.contains(&5));
+
+        assert_eq!(Some(0), set.extract_if(|_| true).next());
+        assert_eq!(vec![&2, &4, &6, &8], set.iter().collect::<Vec<_>>());
+        assert!(!set.is_inline());
+
+        assert_eq!(2, set.extract_if(|v| *v > 4).count());
+        assert_eq!(vec![&2, &4], set.iter().collect::<Vec<_>>());
+        assert!(set.is_inline());
+    }
+
+    #[test]
+    fn bit_assign_operators() {
+        let mut set: SmallSet<_, 4> = smallset! { 1, 2, 3 };
+        let other: SmallSet<_, 2> = SmallSet::from_iter([3, 4, 5]);
+
+        set |= &other;
+        assert_eq!(vec![&1, &2, &3, &4, &5], set.iter().collect::<Vec<_>>());
+        assert!(!set.is_inline());
+
+        set -= &other;
+        assert_eq!(vec![&1, &2], set.iter().collect::<Vec<_>>());
+        assert!(set.is_inline());
+
+        let other: SmallSet<_, 2> = smallset! { 2, 6 };
+        set ^= &other;
+        assert_eq!(vec![&1, &6], set.iter().collect::<Vec<_>>());
+
+        set |= &SmallSet::<_, 2>::from_iter([3, 4, 5]);
+        set &= &SmallSet::<_, 4>::from_iter([6, 5, 0, 3]);
+        assert_eq!(vec![&6, &3, &5], set.iter().collect::<Vec<_>>());
+        assert!(!set.is_inline());
+
+        set ^= &SmallSet::<_, 4>::from_iter([5, 6, 7, 8]);
+        assert_eq!(vec![&3, &7, &8], set.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn difference_in_place_moves_heap_set_inline() {
+        let mut set: SmallSet<_, 4> = SmallSet::from_iter(0..8);
+        let other: SmallSet<_, 2> = SmallSet::from_iter([1, 3, 5, 7, 9]);
+        set.difference_in_place(&other);
+        assert_eq!(vec![&0, &2, &4, &6], set.iter().collect::<Vec<_>>());
+        assert!(!set.is_inline());
+
+        let other: SmallSet<_, 2> = smallset! { 0, 6 };
+        set.difference_in_place(&other);
+        assert_eq!(vec![&2, &4], set.iter().collect::<Vec<_>>());
+        assert!(set.is_inline());
+    }
+
+    #[test]
+    fn retain_in_moves_heap_set_inline() {
+        let mut set: SmallSet<_, 4> = SmallSet::from_iter(0..8);
+        let other: SmallSet<_, 8> = SmallSet::from_iter([9, 6, 1]);
+        set.retain_in(&other);
+        assert_eq!(vec![&1, &6], set.iter().collect::<Vec<_>>());
+        assert!(set.is_inline());
+    }
+
+    #[test]
+    fn retain_moves_data_inline_at_half_capacity() {
+        let mut set: SmallSet<_, 4> = SmallSet::from_iter(0..6);
+        assert!(!set.is_inline());
+
+        set.retain(|v| *v < 3);
+        assert_eq!(vec![&0, &1, &2], set.iter().collect::<Vec<_>>());
+        assert!(!set.is_inline());
+
+        set.retain(|v| *v != 1);
+        assert_eq!(vec![&0, &2], set.iter().collect::<Vec<_>>());
+        assert!(set.is_inline());
+    }
+
+    #[test]
+    fn sort_and_binary_search() {
+        fn test<const C: usize>(inline: bool) {
+            let mut set: SmallSet<_, C> = smallset! { 5, 1, 3, 0 };
+            assert_eq!(inline, set.is_inline());
+
+            set.sort();
+            assert_eq!(vec![&0, &1, &3, &5], set.iter().collect::<Vec<_>>());
+            assert_eq!(Some(&3), set.get_index(2));
+            assert_eq!(Ok(0), set.binary_search(&0));
+            assert_eq!(Ok(3), set.binary_search(&5));
+            assert_eq!(Err(2), set.binary_search(&2));
+            assert_eq!(Err(4), set.binary_search(&6));
+
+            set.sort_by(|a, b| b.cmp(a));
+            assert_eq!(vec![&5, &3, &1, &0], set.iter().collect::<Vec<_>>());
+            assert_eq!(Some(0), set.get_index_of(&5));
+        }
+        test::<2>(false);
+        test::<4>(true);
+    }
+
+    #[test]
+    fn into_vec_test() {
+        fn test<const C: usize>(inline: bool) {
+            let set: SmallSet<_, C> = smallset! { 1, 0, 4 };
+            assert_eq!(inline, set.is_inline());
+            assert_eq!(vec![1, 0, 4], set.into_vec());
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn reverse_test() {
+        fn test<const C: usize>(inline: bool) {
+            let mut set: SmallSet<_, C> = smallset! { 1, 0, 4 };
+            assert_eq!(inline, set.is_inline());
+            set.reverse();
+            assert_eq!(vec![&4, &0, &1], set.iter().collect::<Vec<_>>());
+            assert_eq!(Some(0), set.get_index_of(&4));
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn ord_compares_in_insertion_order() {
+        let inline: SmallSet<_, 3> = smallset! { 1, 0, 4 };
+        // Popping keeps the data on the heap because of the inline threshold.
+        let mut heap: SmallSet<_, 3> = smallset! { 1, 0, 4, 5 };
+        heap.pop();
+        assert!(inline.is_inline());
+        assert!(!heap.is_inline());
+        assert_eq!(Ordering::Equal, inline.cmp(&heap));
+        assert_eq!(Some(Ordering::Equal), heap.partial_cmp(&inline));
+
+        let a: SmallSet<_, 3> = smallset! { 1, 0, 4 };
+        let b: SmallSet<_, 3> = smallset! { 1, 4, 0 };
+        let c: SmallSet<_, 3> = smallset! { 1, 0 };
+        assert_eq!(Ordering::Equal, a.cmp(&inline));
+        assert_eq!(Ordering::Less, a.cmp(&b));
+        assert_eq!(Ordering::Greater, a.cmp(&c));
+        assert_eq!(Some(Ordering::Less), c.partial_cmp(&b));
+
+        let mut sets = vec![b.clone(), a.clone(), c.clone()];
+        sets.sort();
+        assert_eq!(vec![c, a, b], sets);
+    }
+
     #[test]
     fn insert_and_insert_full_tests() {
         // Test cases:
@@ -667,7 +1551,7 @@ mod test {
                 expected_inline_before: true,
                 expected_inline_after: true,
                 expected_values: vec![10, 5, 7],
-                expected_return: (2, false),
+                expected_return: (2, true),
             },
             TestCase {
                 name: "new key/value, move to heap",
@@ -676,7 +1560,7 @@ mod test {
                 expected_inline_before: true,
                 expected_inline_after: false,
                 expected_values: vec![10, 5, 86, 7],
-                expected_return: (3, false),
+                expected_return: (3, true),
             },
             TestCase {
                 name: "new key/value, stay on heap",
@@ -685,7 +1569,7 @@ mod test {
                 expected_inline_before: false,
                 expected_inline_after: false,
                 expected_values: vec![10, 5, 86, 93, 7],
-                expected_return: (4, false),
+                expected_return: (4, true),
             },
             TestCase {
                 name: "overwrite existing key/value, stay inline",
@@ -694,7 +1578,7 @@ mod test {
                 expected_inline_before: true,
                 expected_inline_after: true,
                 expected_values: vec![10, 5, 86],
-                expected_return: (1, true),
+                expected_return: (1, false),
             },
             TestCase {
                 name: "overwrite existing key/value, stay on heap",
@@ -703,7 +1587,7 @@ mod test {
                 expected_inline_before: false,
                 expected_inline_after: false,
                 expected_values: vec![10, 5, 86, 93],
-                expected_return: (0, true),
+                expected_return: (0, false),
             },
         ];
 
@@ -927,6 +1811,70 @@ mod test {
         test::<3>(true);
     }
 
+    #[test]
+    fn replace_returns_the_stored_value() {
+        fn test<const C: usize>(inline: bool) {
+            let mut set: SmallSet<_, C> = smallset! {
+                PayloadValue(1, "a"),
+                PayloadValue(0, "b"),
+                PayloadValue(4, "c"),
+            };
+            assert_eq!(inline, set.is_inline());
+
+            let replaced = set.replace(PayloadValue(0, "new")).unwrap();
+            assert_eq!("b", replaced.1);
+            assert_eq!("new", set.get_index(1).unwrap().1);
+            assert_eq!(3, set.len());
+
+            assert!(set.replace(PayloadValue(7, "d")).is_none());
+            assert_eq!(Some(3), set.get_index_of(&PayloadValue(7, "")));
+            assert!(!set.is_inline());
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    #[test]
+    fn take_returns_the_stored_value() {
+        fn test<const C: usize>(inline: bool) {
+            let mut set: SmallSet<_, C> = smallset! {
+                PayloadValue(1, "a"),
+                PayloadValue(0, "b"),
+                PayloadValue(4, "c"),
+            };
+            assert_eq!(inline, set.is_inline());
+
+            let taken = set.take(&PayloadValue(0, "lookup")).unwrap();
+            assert_eq!("b", taken.1);
+            assert_eq!(2, set.len());
+            assert!(!set.contains(&PayloadValue(0, "")));
+
+            assert!(set.take(&PayloadValue(0, "lookup")).is_none());
+            assert!(set.take(&PayloadValue(9, "lookup")).is_none());
+            assert_eq!(2, set.len());
+        }
+        test::<1>(false);
+        test::<3>(true);
+    }
+
+    // Type whose equality ignores the payload
+    #[derive(Debug)]
+    struct PayloadValue(usize, &'static str);
+
+    impl Hash for PayloadValue {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            self.0.hash(state);
+        }
+    }
+
+    impl PartialEq for PayloadValue {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for PayloadValue {}
+
     // Type for testing equivalence to String
     struct MyType(usize);
 
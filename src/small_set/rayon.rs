@@ -0,0 +1,67 @@
+//! Parallel collection support for [`SmallSet`] with
+//! [rayon](https://docs.rs/rayon/1.7/rayon).
+
+use core::hash::BuildHasher;
+use core::hash::Hash;
+
+use rayon::iter::FromParallelIterator;
+use rayon::iter::IntoParallelIterator;
+use rayon::iter::ParallelExtend;
+use rayon::iter::ParallelIterator;
+
+use super::SmallSet;
+use crate::SmallMap;
+
+/// Collects the values in parallel, see the `FromParallelIterator`
+/// implementation of [`SmallMap`].
+impl<T, const C: usize, S> FromParallelIterator<T> for SmallSet<T, C, S>
+where
+    T: Hash + Eq + Send,
+    S: BuildHasher + Default + Send,
+{
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = T>,
+    {
+        Self::from_keys(SmallMap::from_par_iter(
+            par_iter.into_par_iter().map(|value| (value, ())),
+        ))
+    }
+}
+
+/// Extends the set in parallel, see the `ParallelExtend` implementation of
+/// [`SmallMap`].
+impl<T, const C: usize, S> ParallelExtend<T> for SmallSet<T, C, S>
+where
+    T: Hash + Eq + Send,
+    S: BuildHasher + Default + Send,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = T>,
+    {
+        self.data
+            .par_extend(par_iter.into_par_iter().map(|value| (value, ())));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn collect_and_extend_in_parallel() {
+        let set: SmallSet<_, 8> = (0..10_000).into_par_iter().map(|i| i % 500).collect();
+        assert!(!set.is_inline());
+        assert_eq!(500, set.len());
+        assert!((0..500).all(|i| set.contains(&i)));
+
+        let set: SmallSet<_, 8> = (0..4).into_par_iter().collect();
+        assert!(set.is_inline());
+        assert_eq!(4, set.len());
+
+        let mut set: SmallSet<_, 8> = SmallSet::new();
+        set.par_extend((0..1000).into_par_iter());
+        assert!(set.iter().copied().eq(0..1000));
+    }
+}
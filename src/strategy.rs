@@ -0,0 +1,110 @@
+//! [proptest](https://docs.rs/proptest) strategies for the collections of this
+//! crate.
+//!
+//! The strategies generate a sequence of key-values (or values) and insert
+//! them in order, so the generated collections may be smaller than the
+//! requested size when duplicates are generated. Depending on the size range
+//! and the inline capacity `C`, both inline and heap collections are produced.
+//! Shrinking removes elements, so failing cases shrink toward smaller
+//! collections.
+//!
+//! ```
+//! use std::collections::HashSet;
+//!
+//! use more_collections::strategy;
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn keys_are_unique(map in strategy::small_map::<_, _, 4>(any::<u8>(), any::<u8>(), 0..16)) {
+//!         prop_assert_eq!(map.len(), map.keys().collect::<HashSet<_>>().len());
+//!     }
+//! }
+//! ```
+
+use core::fmt::Debug;
+use core::hash::Hash;
+
+use proptest::collection::vec;
+use proptest::collection::SizeRange;
+use proptest::strategy::Strategy;
+
+use crate::SmallMap;
+#[cfg(feature = "smallset")]
+use crate::SmallSet;
+
+/// Create a strategy that generates a [`SmallMap`] with inline capacity `C`
+/// from up to `size` key-values generated by `key` and `value`.
+pub fn small_map<K, V, const C: usize>(
+    key: K,
+    value: V,
+    size: impl Into<SizeRange>,
+) -> impl Strategy<Value = SmallMap<K::Value, V::Value, C>>
+where
+    K: Strategy,
+    K::Value: Hash + Eq,
+    V: Strategy,
+    V::Value: Debug,
+{
+    vec((key, value), size).prop_map(SmallMap::from_iter)
+}
+
+/// Create a strategy that generates a [`SmallSet`] with inline capacity `C`
+/// from up to `size` values generated by `element`.
+#[cfg(feature = "smallset")]
+pub fn small_set<T, const C: usize>(
+    element: T,
+    size: impl Into<SizeRange>,
+) -> impl Strategy<Value = SmallSet<T::Value, C>>
+where
+    T: Strategy,
+    T::Value: Hash + Eq,
+{
+    vec(element, size).prop_map(SmallSet::from_iter)
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestRunner;
+
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn small_map_iterates_in_insertion_order(
+            map in small_map::<_, _, 4>(any::<u8>(), any::<u8>(), 0..12)
+        ) {
+            prop_assert_eq!(map.len() <= 4, map.is_inline());
+            for (index, (key, value)) in map.iter().enumerate() {
+                prop_assert_eq!(Some((index, key, value)), map.get_full(key));
+            }
+        }
+
+        #[test]
+        fn small_set_iterates_in_insertion_order(set in small_set::<_, 4>(any::<u8>(), 0..12)) {
+            prop_assert_eq!(set.len() <= 4, set.is_inline());
+            for (index, value) in set.iter().enumerate() {
+                prop_assert_eq!(Some(index), set.get_index_of(value));
+            }
+        }
+    }
+
+    #[test]
+    fn strategies_generate_inline_and_heap_collections() {
+        let mut runner = TestRunner::deterministic();
+        let map_strategy = small_map::<_, _, 4>(0..100u32, any::<u8>(), 0..12);
+        let set_strategy = small_set::<_, 4>(0..100u32, 0..12);
+        let mut maps = Vec::new();
+        let mut sets = Vec::new();
+        for _ in 0..64 {
+            maps.push(map_strategy.new_tree(&mut runner).unwrap().current());
+            sets.push(set_strategy.new_tree(&mut runner).unwrap().current());
+        }
+        assert!(maps.iter().any(SmallMap::is_inline));
+        assert!(maps.iter().any(|map| !map.is_inline()));
+        assert!(sets.iter().any(SmallSet::is_inline));
+        assert!(sets.iter().any(|set| !set.is_inline()));
+    }
+}